@@ -0,0 +1,154 @@
+//! Worst-case cost of the timer queue operations that run inside a critical section
+//!
+//! Run it once per queue implementation and compare:
+//!
+//! ``` text
+//! $ cargo bench --bench timer_queue
+//! $ cargo bench --bench timer_queue --features tq-heap
+//! ```
+//!
+//! Every operation is timed on its own. For each queue size the longest operation of a run is
+//! recorded and the shortest of these over several runs is reported, which filters out the noise
+//! of the host OS while keeping the input dependent worst case.
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant as HostInstant},
+};
+
+use rtic::{
+    export::{consts::*, ArrayLength, NotReady, TimerQueue},
+    time::{clock, fraction::Fraction, Clock, Instant},
+    Monotonic,
+};
+
+const RUNS: usize = 20;
+
+static NOW: AtomicU32 = AtomicU32::new(0);
+
+/// A monotonic whose time is set by the benchmark
+struct BenchMono;
+
+impl Clock for BenchMono {
+    type T = u32;
+
+    const SCALING_FACTOR: Fraction = Fraction::new(1, 1_000_000);
+
+    fn try_now(&self) -> Result<Instant<Self>, clock::Error> {
+        Ok(Instant::new(NOW.load(Ordering::Relaxed)))
+    }
+}
+
+impl Monotonic for BenchMono {
+    unsafe fn reset(&mut self) {}
+
+    fn set_compare(&mut self, _: &Instant<Self>) {}
+
+    fn clear_compare_flag(&mut self) {}
+}
+
+#[derive(Default)]
+struct WorstCase {
+    enqueue: Duration,
+    cancel: Duration,
+    dequeue: Duration,
+}
+
+fn time<R>(worst: &mut Duration, f: impl FnOnce() -> R) -> R {
+    let start = HostInstant::now();
+    let r = f();
+    *worst = (*worst).max(start.elapsed());
+    r
+}
+
+fn nr(instant: u32) -> NotReady<BenchMono, ()> {
+    NotReady {
+        index: 0,
+        instant: Instant::new(instant),
+        task: (),
+    }
+}
+
+/// One run: fills the queue in ascending and in descending order, cancels every entry and
+/// finally drains a full queue
+fn run<N>(cap: u32) -> WorstCase
+where
+    N: ArrayLength<rtic::export::Node<NotReady<BenchMono, ()>>>,
+{
+    let mut mono = BenchMono;
+    let mut worst = WorstCase::default();
+    let mut tq: Box<TimerQueue<BenchMono, (), N>> = Box::new(TimerQueue::new());
+
+    NOW.store(0, Ordering::Relaxed);
+
+    // Ascending instants walk a sorted list to its tail, descending ones sift a heap to its root
+    for descending in &[false, true] {
+        let mut keys = Vec::with_capacity(cap as usize);
+
+        for i in 0..cap {
+            let instant = if *descending { cap - i } else { i + 1 };
+
+            keys.push(time(&mut worst.enqueue, || unsafe {
+                tq.enqueue_unchecked(nr(instant), || {}, || {}, Some(&mut mono))
+            }));
+        }
+
+        for key in keys {
+            time(&mut worst.cancel, || tq.cancel(key)).unwrap();
+        }
+    }
+
+    for i in 0..cap {
+        unsafe { tq.enqueue_unchecked(nr(i + 1), || {}, || {}, Some(&mut mono)) };
+    }
+
+    NOW.store(cap, Ordering::Relaxed);
+
+    while time(&mut worst.dequeue, || tq.dequeue(|| {}, &mut mono)).is_some() {}
+
+    worst
+}
+
+fn bench<N>(cap: u32)
+where
+    N: ArrayLength<rtic::export::Node<NotReady<BenchMono, ()>>>,
+{
+    let mut best = WorstCase {
+        enqueue: Duration::MAX,
+        cancel: Duration::MAX,
+        dequeue: Duration::MAX,
+    };
+
+    for _ in 0..RUNS {
+        let worst = run::<N>(cap);
+
+        best.enqueue = best.enqueue.min(worst.enqueue);
+        best.cancel = best.cancel.min(worst.cancel);
+        best.dequeue = best.dequeue.min(worst.dequeue);
+    }
+
+    println!(
+        "{:>8} {:>12?} {:>12?} {:>12?}",
+        cap, best.enqueue, best.cancel, best.dequeue
+    );
+}
+
+fn main() {
+    println!(
+        "timer queue: {}",
+        if cfg!(feature = "tq-heap") {
+            "indexed binary heap"
+        } else {
+            "sorted linked list"
+        }
+    );
+    println!(
+        "{:>8} {:>12} {:>12} {:>12}",
+        "entries", "enqueue", "cancel", "dequeue"
+    );
+
+    bench::<U16>(16);
+    bench::<U64>(64);
+    bench::<U256>(256);
+    bench::<U1024>(1024);
+}
@@ -24,14 +24,12 @@ mod app {
 
         let mono = DwtSystick::new(&mut dcb, dwt, systick, 8_000_000);
 
-        foo::spawn_after(Seconds(1_u32)).unwrap();
-
         (init::LateResources {}, init::Monotonics(mono))
     }
 
-    #[task]
+    // Released every second, starting one second after `init` returns
+    #[task(period = Seconds(1_u32), offset = Seconds(1_u32))]
     fn foo(_cx: foo::Context) {
-        // Periodic
-        foo::spawn_after(Seconds(1_u32)).unwrap();
+        // Use `foo::stop()` and `foo::start()` to pause and resume the releases
     }
 }
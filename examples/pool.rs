@@ -5,32 +5,19 @@
 #![no_main]
 #![no_std]
 
-use heapless::{
-    pool,
-    pool::singleton::{Box, Pool},
-};
 use panic_semihosting as _;
 use rtic::app;
 
-// Declare a pool of 128-byte memory blocks
-pool!(P: [u8; 128]);
-
 #[app(device = lm3s6965, dispatchers = [SSI0, QEI0])]
+// Declare a pool of 4 blocks of 128 bytes
+#[pool([u8; 128], 4)]
 mod app {
-    use crate::{Box, Pool};
     use cortex_m_semihosting::{debug, hprintln};
     use lm3s6965::Interrupt;
-
-    // Import the memory pool into scope
-    use super::P;
+    use rtic::Box;
 
     #[init]
     fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
-        static mut MEMORY: [u8; 512] = [0; 512];
-
-        // Increase the capacity of the memory pool by ~4
-        P::grow(MEMORY);
-
         rtic::pend(Interrupt::I2C0);
 
         (init::LateResources {}, init::Monotonics())
@@ -38,18 +25,16 @@ mod app {
 
     #[task(binds = I2C0, priority = 2)]
     fn i2c0(_: i2c0::Context) {
-        // claim a memory block, leave it uninitialized and ..
-        let x = P::alloc().unwrap().freeze();
-
-        // .. send it to the `foo` task
-        foo::spawn(x).ok().unwrap();
+        // move a buffer into a memory block and send it to the `foo` task
+        foo::spawn_boxed([0; 128]).ok().unwrap();
 
-        // send another block to the task `bar`
-        bar::spawn(P::alloc().unwrap().freeze()).ok().unwrap();
+        // claim another block and send it to the task `bar`
+        let x = pools::alloc([1; 128]).ok().unwrap();
+        bar::spawn(x).ok().unwrap();
     }
 
     #[task]
-    fn foo(_: foo::Context, x: Box<P>) {
+    fn foo(_: foo::Context, x: Box<[u8; 128]>) {
         hprintln!("foo({:?})", x.as_ptr()).unwrap();
 
         // explicitly return the block to the pool
@@ -59,7 +44,7 @@ mod app {
     }
 
     #[task(priority = 2)]
-    fn bar(_: bar::Context, x: Box<P>) {
+    fn bar(_: bar::Context, x: Box<[u8; 128]>) {
         hprintln!("bar({:?})", x.as_ptr()).unwrap();
 
         // this is done automatically so we can omit the call to `drop`
@@ -10,7 +10,7 @@ use panic_semihosting as _;
 #[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
 mod app {
     use dwt_systick_monotonic::DwtSystick;
-    use rtic::time::duration::Seconds;
+    use rtic::{time::duration::Seconds, SpawnError};
 
     #[monotonic(binds = SysTick, default = true)]
     type MyMono = DwtSystick<8_000_000>; // 8 MHz
@@ -26,93 +26,103 @@ mod app {
         // Task without message passing
 
         // Not default
-        let _: Result<foo::MyMono::SpawnHandle, ()> =
+        let _: Result<foo::MyMono::SpawnHandle, SpawnError<()>> =
             foo::MyMono::spawn_at(monotonics::MyMono::now());
-        let handle: Result<foo::MyMono::SpawnHandle, ()> = foo::MyMono::spawn_after(Seconds(1_u32));
-        let _: Result<foo::MyMono::SpawnHandle, ()> =
+        let handle: Result<foo::MyMono::SpawnHandle, SpawnError<()>> =
+            foo::MyMono::spawn_after(Seconds(1_u32));
+        let _: Result<foo::MyMono::SpawnHandle, SpawnError<foo::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<foo::MyMono::SpawnHandle, ()> = foo::MyMono::spawn_after(Seconds(1_u32));
-        let _: Result<foo::MyMono::SpawnHandle, ()> =
+        let handle: Result<foo::MyMono::SpawnHandle, SpawnError<()>> =
+            foo::MyMono::spawn_after(Seconds(1_u32));
+        let _: Result<foo::MyMono::SpawnHandle, SpawnError<foo::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<foo::MyMono::SpawnHandle, ()> = foo::MyMono::spawn_after(Seconds(1_u32));
-        let _: Result<(), ()> = handle.unwrap().cancel();
+        let handle: Result<foo::MyMono::SpawnHandle, SpawnError<()>> =
+            foo::MyMono::spawn_after(Seconds(1_u32));
+        let _: Result<(), SpawnError<()>> = handle.unwrap().cancel();
 
         // Using default
-        let _: Result<foo::SpawnHandle, ()> = foo::spawn_at(monotonics::now());
-        let handle: Result<foo::SpawnHandle, ()> = foo::spawn_after(Seconds(1_u32));
-        let _: Result<foo::SpawnHandle, ()> = handle.unwrap().reschedule_after(Seconds(1_u32));
+        let _: Result<foo::SpawnHandle, SpawnError<()>> = foo::spawn_at(monotonics::now());
+        let handle: Result<foo::SpawnHandle, SpawnError<()>> = foo::spawn_after(Seconds(1_u32));
+        let _: Result<foo::SpawnHandle, SpawnError<foo::SpawnHandle>> =
+            handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<foo::SpawnHandle, ()> = foo::spawn_after(Seconds(1_u32));
-        let _: Result<foo::SpawnHandle, ()> =
+        let handle: Result<foo::SpawnHandle, SpawnError<()>> = foo::spawn_after(Seconds(1_u32));
+        let _: Result<foo::SpawnHandle, SpawnError<foo::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<foo::SpawnHandle, ()> = foo::spawn_after(Seconds(1_u32));
-        let _: Result<(), ()> = handle.unwrap().cancel();
+        let handle: Result<foo::SpawnHandle, SpawnError<()>> = foo::spawn_after(Seconds(1_u32));
+        let _: Result<(), SpawnError<()>> = handle.unwrap().cancel();
 
         // Task with single message passing
 
         // Not default
-        let _: Result<bar::MyMono::SpawnHandle, u32> =
+        let _: Result<bar::MyMono::SpawnHandle, SpawnError<u32>> =
             bar::MyMono::spawn_at(monotonics::MyMono::now(), 0);
-        let handle: Result<bar::MyMono::SpawnHandle, u32> =
+        let handle: Result<bar::MyMono::SpawnHandle, SpawnError<u32>> =
             bar::MyMono::spawn_after(Seconds(1_u32), 0);
-        let _: Result<bar::MyMono::SpawnHandle, ()> =
+        let _: Result<bar::MyMono::SpawnHandle, SpawnError<bar::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<bar::MyMono::SpawnHandle, u32> =
+        let handle: Result<bar::MyMono::SpawnHandle, SpawnError<u32>> =
             bar::MyMono::spawn_after(Seconds(1_u32), 0);
-        let _: Result<bar::MyMono::SpawnHandle, ()> =
+        let _: Result<bar::MyMono::SpawnHandle, SpawnError<bar::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<bar::MyMono::SpawnHandle, u32> =
+        let handle: Result<bar::MyMono::SpawnHandle, SpawnError<u32>> =
             bar::MyMono::spawn_after(Seconds(1_u32), 0);
-        let _: Result<u32, ()> = handle.unwrap().cancel();
+        let _: Result<u32, SpawnError<()>> = handle.unwrap().cancel();
 
         // Using default
-        let _: Result<bar::SpawnHandle, u32> = bar::spawn_at(monotonics::MyMono::now(), 0);
-        let handle: Result<bar::SpawnHandle, u32> = bar::spawn_after(Seconds(1_u32), 0);
-        let _: Result<bar::SpawnHandle, ()> = handle.unwrap().reschedule_after(Seconds(1_u32));
+        let _: Result<bar::SpawnHandle, SpawnError<u32>> =
+            bar::spawn_at(monotonics::MyMono::now(), 0);
+        let handle: Result<bar::SpawnHandle, SpawnError<u32>> = bar::spawn_after(Seconds(1_u32), 0);
+        let _: Result<bar::SpawnHandle, SpawnError<bar::SpawnHandle>> =
+            handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<bar::SpawnHandle, u32> = bar::spawn_after(Seconds(1_u32), 0);
-        let _: Result<bar::SpawnHandle, ()> =
+        let handle: Result<bar::SpawnHandle, SpawnError<u32>> = bar::spawn_after(Seconds(1_u32), 0);
+        let _: Result<bar::SpawnHandle, SpawnError<bar::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<bar::SpawnHandle, u32> = bar::spawn_after(Seconds(1_u32), 0);
-        let _: Result<u32, ()> = handle.unwrap().cancel();
+        let handle: Result<bar::SpawnHandle, SpawnError<u32>> = bar::spawn_after(Seconds(1_u32), 0);
+        let _: Result<u32, SpawnError<()>> = handle.unwrap().cancel();
 
         // Task with multiple message passing
 
         // Not default
-        let _: Result<baz::MyMono::SpawnHandle, (u32, u32)> =
+        let _: Result<baz::MyMono::SpawnHandle, SpawnError<(u32, u32)>> =
             baz::MyMono::spawn_at(monotonics::MyMono::now(), 0, 1);
-        let handle: Result<baz::MyMono::SpawnHandle, (u32, u32)> =
+        let handle: Result<baz::MyMono::SpawnHandle, SpawnError<(u32, u32)>> =
             baz::MyMono::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<baz::MyMono::SpawnHandle, ()> =
+        let _: Result<baz::MyMono::SpawnHandle, SpawnError<baz::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<baz::MyMono::SpawnHandle, (u32, u32)> =
+        let handle: Result<baz::MyMono::SpawnHandle, SpawnError<(u32, u32)>> =
             baz::MyMono::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<baz::MyMono::SpawnHandle, ()> =
+        let _: Result<baz::MyMono::SpawnHandle, SpawnError<baz::MyMono::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<baz::MyMono::SpawnHandle, (u32, u32)> =
+        let handle: Result<baz::MyMono::SpawnHandle, SpawnError<(u32, u32)>> =
             baz::MyMono::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<(u32, u32), ()> = handle.unwrap().cancel();
+        let _: Result<(u32, u32), SpawnError<()>> = handle.unwrap().cancel();
 
         // Using default
-        let _: Result<baz::SpawnHandle, (u32, u32)> =
+        let _: Result<baz::SpawnHandle, SpawnError<(u32, u32)>> =
             baz::spawn_at(monotonics::MyMono::now(), 0, 1);
-        let handle: Result<baz::SpawnHandle, (u32, u32)> = baz::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<baz::SpawnHandle, ()> = handle.unwrap().reschedule_after(Seconds(1_u32));
+        let handle: Result<baz::SpawnHandle, SpawnError<(u32, u32)>> =
+            baz::spawn_after(Seconds(1_u32), 0, 1);
+        let _: Result<baz::SpawnHandle, SpawnError<baz::SpawnHandle>> =
+            handle.unwrap().reschedule_after(Seconds(1_u32));
 
-        let handle: Result<baz::SpawnHandle, (u32, u32)> = baz::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<baz::SpawnHandle, ()> =
+        let handle: Result<baz::SpawnHandle, SpawnError<(u32, u32)>> =
+            baz::spawn_after(Seconds(1_u32), 0, 1);
+        let _: Result<baz::SpawnHandle, SpawnError<baz::SpawnHandle>> =
             handle.unwrap().reschedule_at(monotonics::MyMono::now());
 
-        let handle: Result<baz::SpawnHandle, (u32, u32)> = baz::spawn_after(Seconds(1_u32), 0, 1);
-        let _: Result<(u32, u32), ()> = handle.unwrap().cancel();
+        let handle: Result<baz::SpawnHandle, SpawnError<(u32, u32)>> =
+            baz::spawn_after(Seconds(1_u32), 0, 1);
+        let _: Result<(u32, u32), SpawnError<()>> = handle.unwrap().cancel();
 
         (init::LateResources {}, init::Monotonics(mono))
     }
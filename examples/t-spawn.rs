@@ -9,20 +9,25 @@ use panic_semihosting as _;
 
 #[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
 mod app {
+    use rtic::SpawnError;
+
     #[init]
     fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
-        let _: Result<(), ()> = foo::spawn();
-        let _: Result<(), u32> = bar::spawn(0);
-        let _: Result<(), (u32, u32)> = baz::spawn(0, 1);
+        let _: Result<foo::ReadyHandle, SpawnError<()>> = foo::spawn();
+        let _: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<baz::ReadyHandle, SpawnError<(u32, u32)>> = baz::spawn(0, 1);
+
+        let handle: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<u32, SpawnError<()>> = handle.unwrap().cancel();
 
         (init::LateResources {}, init::Monotonics())
     }
 
     #[idle]
     fn idle(_: idle::Context) -> ! {
-        let _: Result<(), ()> = foo::spawn();
-        let _: Result<(), u32> = bar::spawn(0);
-        let _: Result<(), (u32, u32)> = baz::spawn(0, 1);
+        let _: Result<foo::ReadyHandle, SpawnError<()>> = foo::spawn();
+        let _: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<baz::ReadyHandle, SpawnError<(u32, u32)>> = baz::spawn(0, 1);
 
         loop {
             cortex_m::asm::nop();
@@ -31,23 +36,23 @@ mod app {
 
     #[task(binds = SVCall)]
     fn svcall(_: svcall::Context) {
-        let _: Result<(), ()> = foo::spawn();
-        let _: Result<(), u32> = bar::spawn(0);
-        let _: Result<(), (u32, u32)> = baz::spawn(0, 1);
+        let _: Result<foo::ReadyHandle, SpawnError<()>> = foo::spawn();
+        let _: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<baz::ReadyHandle, SpawnError<(u32, u32)>> = baz::spawn(0, 1);
     }
 
     #[task(binds = UART0)]
     fn uart0(_: uart0::Context) {
-        let _: Result<(), ()> = foo::spawn();
-        let _: Result<(), u32> = bar::spawn(0);
-        let _: Result<(), (u32, u32)> = baz::spawn(0, 1);
+        let _: Result<foo::ReadyHandle, SpawnError<()>> = foo::spawn();
+        let _: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<baz::ReadyHandle, SpawnError<(u32, u32)>> = baz::spawn(0, 1);
     }
 
     #[task]
     fn foo(_: foo::Context) {
-        let _: Result<(), ()> = foo::spawn();
-        let _: Result<(), u32> = bar::spawn(0);
-        let _: Result<(), (u32, u32)> = baz::spawn(0, 1);
+        let _: Result<foo::ReadyHandle, SpawnError<()>> = foo::spawn();
+        let _: Result<bar::ReadyHandle, SpawnError<u32>> = bar::spawn(0);
+        let _: Result<baz::ReadyHandle, SpawnError<(u32, u32)>> = baz::spawn(0, 1);
     }
 
     #[task]
@@ -12,6 +12,10 @@ use syn::Ident;
 pub struct Analysis {
     parent: P<analyze::Analysis>,
     pub interrupts: BTreeMap<Priority, (Ident, ExternInterrupt)>,
+    /// Priority levels whose dispatcher was marked `#[edf]`
+    pub edf: BTreeSet<Priority>,
+    /// Priority levels served by the dispatcher marked `#[multiplex]`
+    pub multiplexed: BTreeSet<Priority>,
 }
 
 impl ops::Deref for Analysis {
@@ -31,17 +35,56 @@ pub fn app(analysis: P<analyze::Analysis>, app: &App) -> P<Analysis> {
         .map(|task| task.args.priority)
         .collect::<BTreeSet<_>>();
 
-    // map from priorities to interrupts (holding name and attributes)
-    let interrupts: BTreeMap<Priority, _> = priorities
-        .iter()
-        .cloned()
-        .rev()
-        .zip(&app.args.extern_interrupts)
-        .map(|(p, (id, ext))| (p, (id.clone(), ext.clone())))
-        .collect();
+    // map from priorities to interrupts (holding name and attributes); a `#[multiplex]` dispatcher
+    // (the last one) serves its level and all the levels below it that are left
+    let mut edf = BTreeSet::new();
+    let mut multiplexed = BTreeSet::new();
+    let mut dispatchers = app.args.extern_interrupts.iter();
+    let mut multiplex = None;
+    let mut interrupts = BTreeMap::new();
+    for p in priorities.iter().cloned().rev() {
+        let (id, ext, is_edf) = match multiplex.clone() {
+            Some(dispatcher) => {
+                multiplexed.insert(p);
+
+                dispatcher
+            }
+
+            None => {
+                let (id, ext) = match dispatchers.next() {
+                    Some(dispatcher) => dispatcher,
+                    None => break,
+                };
+                let mut ext = ext.clone();
+
+                // `#[edf]` selects the ready queue and `#[multiplex]` the handler, they're not
+                // forwarded to it
+                let len = ext.attrs.len();
+                ext.attrs.retain(|attr| !attr.path.is_ident("edf"));
+                let is_edf = ext.attrs.len() != len;
+
+                let len = ext.attrs.len();
+                ext.attrs.retain(|attr| !attr.path.is_ident("multiplex"));
+                if ext.attrs.len() != len {
+                    multiplexed.insert(p);
+                    multiplex = Some((id.clone(), ext.clone(), is_edf));
+                }
+
+                (id.clone(), ext, is_edf)
+            }
+        };
+
+        if is_edf {
+            edf.insert(p);
+        }
+
+        interrupts.insert(p, (id, ext));
+    }
 
     P::new(Analysis {
         parent: analysis,
         interrupts,
+        edf,
+        multiplexed,
     })
 }
@@ -1,35 +1,67 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use proc_macro2::Span;
-use rtic_syntax::{analyze::Analysis, ast::App};
-use syn::{parse, Path};
+use quote::ToTokens;
+use rtic_syntax::{
+    analyze::Analysis,
+    ast::{App, ExternInterrupt},
+};
+use syn::{parse, Ident, Path};
+
+use crate::syntax::{Channel, Extensions, Overflow, Pool, TaskArgs};
 
 pub struct Extra {
     pub device: Path,
     pub peripherals: bool,
+    /// Hook called on a `max_latency` miss
+    pub deadline_miss: Option<Path>,
+    /// Hook called on a `budget` overrun
+    pub budget_overrun: Option<Path>,
+    /// Type that implements `rtic::Tracer`
+    pub tracer: Option<Path>,
+    /// Port specific arguments of every task
+    pub tasks: HashMap<Ident, TaskArgs>,
+    /// Channels between tasks
+    pub channels: Vec<Channel>,
+    /// Memory pools
+    pub pools: Vec<Pool>,
 }
 
-pub fn app(app: &App, _analysis: &Analysis) -> parse::Result<Extra> {
+pub fn app(app: &App, _analysis: &Analysis, extensions: Extensions) -> parse::Result<Extra> {
     // Check that external (device-specific) interrupts are not named after known (Cortex-M)
-    // exceptions
+    // exceptions, except for the exceptions that software can pend and that are free to use
     for name in app.args.extern_interrupts.keys() {
         let name_s = name.to_string();
 
         match &*name_s {
             "NonMaskableInt" | "HardFault" | "MemoryManagement" | "BusFault" | "UsageFault"
-            | "SecureFault" | "SVCall" | "DebugMonitor" | "PendSV" | "SysTick" => {
+            | "SecureFault" | "DebugMonitor" | "SysTick" => {
                 return Err(parse::Error::new(
                     name.span(),
-                    "Cortex-M exceptions can't be used as `extern` interrupts",
+                    "Cortex-M exceptions can't be used as `extern` interrupts, \
+                     except for `PendSV` and `SVCall`",
                 ));
             }
 
+            "PendSV" | "SVCall" => {
+                if app
+                    .monotonics
+                    .values()
+                    .any(|monotonic| monotonic.args.binds == *name)
+                {
+                    return Err(parse::Error::new(
+                        name.span(),
+                        "this exception is already bound by a monotonic",
+                    ));
+                }
+            }
+
             _ => {}
         }
     }
 
     // Check that there are enough external interrupts to dispatch the software tasks and the timer
-    // queue handler
+    // queue handler, or that the last one is multiplexed
     let mut first = None;
     let priorities = app
         .software_tasks
@@ -40,13 +72,36 @@ pub fn app(app: &App, _analysis: &Analysis) -> parse::Result<Extra> {
         })
         .collect::<HashSet<_>>();
 
-    let need = priorities.len();
+    let is_multiplexed = |interrupt: &ExternInterrupt| {
+        interrupt
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("multiplex"))
+    };
     let given = app.args.extern_interrupts.len();
-    if need > given {
+    for (i, (name, interrupt)) in app.args.extern_interrupts.iter().enumerate() {
+        if is_multiplexed(interrupt) && i + 1 != given {
+            return Err(parse::Error::new(
+                name.span(),
+                "only the last dispatcher can be `#[multiplex]`; \
+                 it serves the priority levels that are left",
+            ));
+        }
+    }
+    let multiplex = app
+        .args
+        .extern_interrupts
+        .values()
+        .last()
+        .map_or(false, is_multiplexed);
+
+    let need = priorities.len();
+    if need > given && !(multiplex && given > 0) {
         let s = {
             format!(
                 "not enough interrupts to dispatch \
-                    all software tasks (need: {}; given: {})",
+                    all software tasks (need: {}; given: {}); mark the last dispatcher \
+                    `#[multiplex]` to have it serve several priority levels",
                 need, given
             )
         };
@@ -56,6 +111,21 @@ pub fn app(app: &App, _analysis: &Analysis) -> parse::Result<Extra> {
         return Err(parse::Error::new(first.unwrap().span(), &s));
     }
 
+    // The multiplexed dispatcher keeps the pending levels in a 32-bit mask
+    if multiplex && priorities.iter().any(|&priority| priority > 32) {
+        let (name, _) = app
+            .args
+            .extern_interrupts
+            .iter()
+            .last()
+            .expect("UNREACHABLE");
+
+        return Err(parse::Error::new(
+            name.span(),
+            "`#[multiplex]` dispatchers only serve priority levels up to 32",
+        ));
+    }
+
     // Check that all exceptions are valid; only exceptions with configurable priorities are
     // accepted
     for (name, task) in &app.hardware_tasks {
@@ -72,10 +142,271 @@ pub fn app(app: &App, _analysis: &Analysis) -> parse::Result<Extra> {
         }
     }
 
+    let has_default_monotonic = app
+        .monotonics
+        .values()
+        .any(|monotonic| monotonic.args.default);
+
+    // Check that the lateness of tasks with a `max_latency` can be measured
+    for (name, args) in &extensions.tasks {
+        if args.max_latency.is_none() {
+            continue;
+        }
+
+        if !app.software_tasks.contains_key(name) {
+            return Err(parse::Error::new(
+                name.span(),
+                "`max_latency` can only be used on software tasks",
+            ));
+        }
+
+        if !has_default_monotonic {
+            return Err(parse::Error::new(
+                name.span(),
+                "lateness is measured on the default monotonic; \
+                 mark one `#[monotonic]` with `default = true`",
+            ));
+        }
+    }
+
+    // Check that the execution time of tasks with a budget can be measured
+    for (name, args) in &extensions.tasks {
+        if args.budget.is_some() && args.budget_cycles.is_some() {
+            return Err(parse::Error::new(
+                name.span(),
+                "`budget` and `budget_cycles` can't be used together",
+            ));
+        }
+
+        if args.budget.is_some() && !has_default_monotonic {
+            return Err(parse::Error::new(
+                name.span(),
+                "`budget` is measured on the default monotonic; \
+                 mark one `#[monotonic]` with `default = true` or use `budget_cycles`",
+            ));
+        }
+    }
+
+    // Check that EDF dispatchers can time stamp the tasks they dispatch
+    for (name, interrupt) in &app.args.extern_interrupts {
+        if interrupt.attrs.iter().any(|attr| attr.path.is_ident("edf")) && !has_default_monotonic {
+            return Err(parse::Error::new(
+                name.span(),
+                "deadlines are taken from the default monotonic; \
+                 mark one `#[monotonic]` with `default = true`",
+            ));
+        }
+    }
+
+    // Check that overflow policies have a ready queue to work on
+    for (name, args) in &extensions.tasks {
+        if args.overflow.is_none() {
+            continue;
+        }
+
+        if !app.software_tasks.contains_key(name) {
+            return Err(parse::Error::new(
+                name.span(),
+                "`overflow` can only be used on software tasks",
+            ));
+        }
+
+        if args.period.is_some() && args.overflow != Some(Overflow::Error) {
+            return Err(parse::Error::new(
+                name.span(),
+                "periodic tasks can't evict or replace their releases",
+            ));
+        }
+    }
+
+    // Check that the messages of `by_ref` tasks stay in the message buffer while the task runs
+    for (name, args) in &extensions.tasks {
+        if args.by_ref && args.is_async {
+            return Err(parse::Error::new(
+                name.span(),
+                "`async` tasks can't borrow their messages; drop `by_ref`",
+            ));
+        }
+    }
+
+    // Check that `async` tasks can be polled by their dispatcher; instances of them run interleaved,
+    // so they can't hold on to anything that's not behind a lock across an `.await`
+    for (name, args) in &extensions.tasks {
+        if !args.is_async {
+            continue;
+        }
+
+        let task = if let Some(task) = app.software_tasks.get(name) {
+            task
+        } else {
+            return Err(parse::Error::new(
+                name.span(),
+                "only software tasks can be `async`",
+            ));
+        };
+
+        if args.period.is_some() {
+            return Err(parse::Error::new(
+                name.span(),
+                "`async` tasks can't be periodic",
+            ));
+        }
+
+        if args.has_budget() {
+            return Err(parse::Error::new(
+                name.span(),
+                "the execution time of `async` tasks can't be budgeted",
+            ));
+        }
+
+        if !task.locals.is_empty() {
+            return Err(parse::Error::new(
+                name.span(),
+                "`async` tasks can't have `static mut` variables; use a resource instead",
+            ));
+        }
+
+        for (resource, _) in &task.args.resources {
+            let (res, _) = app.resource(resource).expect("UNREACHABLE");
+
+            if res.properties.task_local || res.properties.lock_free {
+                return Err(parse::Error::new(
+                    resource.span(),
+                    "`async` tasks can't access `#[task_local]` or `#[lock_free]` resources",
+                ));
+            }
+        }
+    }
+
+    // Check that the timer queues can be sized; they have room for every instance of a software
+    // task plus a delay per instance of an `async` task, and `typenum` counts up to 1024
+    if let Some(monotonic) = app.monotonics.values().next() {
+        let capacity = app
+            .software_tasks
+            .iter()
+            .map(|(name, task)| {
+                let capacity = usize::from(task.args.capacity);
+
+                if extensions.tasks[name].is_async {
+                    2 * capacity
+                } else {
+                    capacity
+                }
+            })
+            .sum::<usize>();
+
+        if capacity > 1024 {
+            return Err(parse::Error::new(
+                monotonic.ident.span(),
+                format!(
+                    "the timer queue needs room for {} entries, more than the 1024 it can have; \
+                     lower the `capacity` of the software tasks",
+                    capacity
+                ),
+            ));
+        }
+    }
+
+    // Check that channels are declared and that they spawn tasks that can be spawned without a
+    // message
+    for (name, args) in &extensions.tasks {
+        let is_task =
+            app.hardware_tasks.contains_key(name) || app.software_tasks.contains_key(name);
+
+        if !is_task && args.has_budget() {
+            return Err(parse::Error::new(
+                name.span(),
+                "the execution time of `#[idle]` can't be budgeted",
+            ));
+        }
+
+        for channel in args.senders.iter().chain(&args.receivers) {
+            if !extensions.channels.iter().any(|c| c.name == *channel) {
+                return Err(parse::Error::new(
+                    channel.span(),
+                    "this channel has not been declared in the `#[channels]` struct",
+                ));
+            }
+        }
+    }
+
+    for channel in &extensions.channels {
+        if let Some(spawn) = &channel.spawn {
+            match app.software_tasks.get(spawn) {
+                Some(task) if task.inputs.is_empty() => {}
+                _ => {
+                    return Err(parse::Error::new(
+                        spawn.span(),
+                        "channels can only spawn software tasks that take no message arguments",
+                    ))
+                }
+            }
+        }
+    }
+
+    // Check that a type has at most one pool; pools are looked up by the type of their blocks
+    for (i, pool) in extensions.pools.iter().enumerate() {
+        let ty = pool.ty.to_token_stream().to_string();
+
+        if extensions.pools[..i]
+            .iter()
+            .any(|other| other.ty.to_token_stream().to_string() == ty)
+        {
+            return Err(parse::Error::new_spanned(
+                &pool.ty,
+                "a pool for this type has already been declared",
+            ));
+        }
+    }
+
+    // Check that periodic tasks can be (re-)scheduled by the runtime
+    for (name, args) in &extensions.tasks {
+        if args.period.is_none() {
+            if args.offset.is_some() {
+                return Err(parse::Error::new(
+                    name.span(),
+                    "`offset` can only be used together with `period`",
+                ));
+            }
+
+            continue;
+        }
+
+        let task = if let Some(task) = app.software_tasks.get(name) {
+            task
+        } else {
+            return Err(parse::Error::new(
+                name.span(),
+                "only software tasks can be periodic",
+            ));
+        };
+
+        if !task.inputs.is_empty() {
+            return Err(parse::Error::new(
+                name.span(),
+                "periodic tasks can't take message arguments",
+            ));
+        }
+
+        if !has_default_monotonic {
+            return Err(parse::Error::new(
+                name.span(),
+                "periodic tasks are scheduled on the default monotonic; \
+                 mark one `#[monotonic]` with `default = true`",
+            ));
+        }
+    }
+
     if let Some(device) = app.args.device.clone() {
         Ok(Extra {
             device,
             peripherals: app.args.peripherals,
+            deadline_miss: extensions.deadline_miss,
+            budget_overrun: extensions.budget_overrun,
+            tracer: extensions.tracer,
+            tasks: extensions.tasks,
+            channels: extensions.channels,
+            pools: extensions.pools,
         })
     } else {
         Err(parse::Error::new(
@@ -5,17 +5,20 @@ use rtic_syntax::ast::App;
 use crate::{analyze::Analysis, check::Extra};
 
 mod assertions;
+mod channels;
 mod dispatchers;
 mod hardware_tasks;
 mod idle;
 mod init;
 mod locals;
 mod module;
+mod pools;
 mod post_init;
 mod pre_init;
 mod resources;
 mod resources_struct;
 mod software_tasks;
+mod stats;
 mod timer_queue;
 mod util;
 
@@ -27,13 +30,13 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
     let mut user = vec![];
 
     // Generate the `main` function
-    let assertion_stmts = assertions::codegen(app, analysis);
+    let assertion_stmts = assertions::codegen(app, analysis, extra);
 
     let pre_init_stmts = pre_init::codegen(app, analysis, extra);
 
     let (mod_app_init, root_init, user_init, call_init) = init::codegen(app, analysis, extra);
 
-    let post_init_stmts = post_init::codegen(app, analysis);
+    let post_init_stmts = post_init::codegen(app, analysis, extra);
 
     let (mod_app_idle, root_idle, user_idle, call_idle) = idle::codegen(app, analysis, extra);
 
@@ -56,35 +59,61 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
     ));
 
     let main = util::suffixed("main");
-    mains.push(quote!(
-        #[doc(hidden)]
-        mod rtic_ext {
-            use super::*;
-            #[no_mangle]
-            unsafe extern "C" fn #main() -> ! {
-                #(#assertion_stmts)*
-
-                #(#pre_init_stmts)*
-
-                #[inline(never)]
-                fn __rtic_init_resources<F>(f: F) where F: FnOnce() {
-                    f();
-                }
+    let body = quote!(
+        #(#assertion_stmts)*
+
+        #(#pre_init_stmts)*
+
+        #[inline(never)]
+        fn __rtic_init_resources<F>(f: F) where F: FnOnce() {
+            f();
+        }
 
-                // Wrap late_init_stmts in a function to ensure that stack space is reclaimed.
-                __rtic_init_resources(||{
-                    #call_init
+        // Wrap late_init_stmts in a function to ensure that stack space is reclaimed.
+        __rtic_init_resources(||{
+            #call_init
 
-                    #(#post_init_stmts)*
-                });
+            #(#post_init_stmts)*
+        });
 
-                #call_idle
+        #call_idle
+    );
+
+    if cfg!(feature = "host") {
+        // On the host the entry point is called by the test harness (or the user's `main`)
+        mains.push(quote!(
+            #[doc(hidden)]
+            pub mod rtic_ext {
+                use super::*;
+                pub unsafe fn #main() -> ! {
+                    #body
+                }
             }
-        }
-    ));
+
+            /// Runs the application on the host until it has nothing left to do
+            pub fn run() {
+                rtic::host::run(rtic_ext::#main)
+            }
+        ));
+    } else {
+        mains.push(quote!(
+            #[doc(hidden)]
+            mod rtic_ext {
+                use super::*;
+                #[no_mangle]
+                unsafe extern "C" fn #main() -> ! {
+                    #body
+                }
+            }
+        ));
+    }
 
     let (mod_app_resources, mod_resources) = resources::codegen(app, analysis, extra);
 
+    let mod_app_channels = channels::codegen(extra);
+
+    let (mod_app_pools, mod_pools) = pools::codegen(extra);
+
     let (mod_app_hardware_tasks, root_hardware_tasks, user_hardware_tasks) =
         hardware_tasks::codegen(app, analysis, extra);
 
@@ -93,6 +122,7 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
 
     let mod_app_dispatchers = dispatchers::codegen(app, analysis, extra);
     let mod_app_timer_queue = timer_queue::codegen(app, analysis, extra);
+    let mod_app_stats = stats::codegen(app, analysis, extra);
     let user_imports = &app.user_imports;
     let user_code = &app.user_code;
     let name = &app.name;
@@ -114,9 +144,111 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
                 "This module holds the static implementation for `{}::now()`",
                 name_str
             );
+            let tq = util::tq_ident(&name_str);
+            let tq = util::mark_internal_ident(&tq);
+            let rt_err = util::rt_err_ident();
+            let (enable_interrupt, pend) = util::monotonic_enable_and_pend(monotonic);
+            let id = if extra.tracer.is_some() {
+                let id = util::task_id(app, name);
+
+                quote!(
+                    /// ID of the timer queue handler, as passed to the `rtic::Tracer` hooks
+                    pub const ID: u16 = #id;
+                )
+            } else {
+                quote!()
+            };
+
+            let has_async_tasks = util::has_async_tasks(app, extra);
+            let default_monotonic = match (monotonic.args.default, has_async_tasks) {
+                (true, true) => {
+                    quote!(pub use #name::{delay, delay_until, next_deadline, now, sleep};)
+                }
+                (true, false) => quote!(pub use #name::{next_deadline, now, sleep};),
+                (false, _) => quote!(),
+            };
+
+            // Delays use the timer queue, so they're only there for `async` tasks to await
+            let delays = if has_async_tasks {
+                let t = util::schedule_t_ident();
+                let delay = util::delay_variant_ident();
+                let ops = util::delay_ops_ident(&name_str);
+                let ops = util::mark_internal_ident(&ops);
+                let (record, fail) = if cfg!(feature = "stats") {
+                    let stats = util::stats_ident(&util::tq_ident(&name_str));
+                    let stats = util::mark_internal_ident(&stats);
+
+                    (
+                        quote!(super::super::#stats.record(tq.0.len());),
+                        quote!(super::super::#stats.fail();),
+                    )
+                } else {
+                    (quote!(), quote!())
+                };
+
+                quote! {
+                    /// Returns a future that completes once this monotonic has reached `instant`
+                    pub fn delay_until(
+                        instant: rtic::time::Instant<super::super::#name>,
+                    ) -> rtic::Delay<super::super::#name> {
+                        rtic::Delay::new(instant, &#ops)
+                    }
+
+                    /// Returns a future that completes after `duration` has passed
+                    pub fn delay<D>(duration: D) -> rtic::Delay<super::super::#name>
+                        where D: rtic::time::duration::Duration + rtic::time::fixed_point::FixedPoint,
+                            D::T: Into<<super::super::#name as rtic::time::Clock>::T>,
+                    {
+                        delay_until(now() + duration)
+                    }
+
+                    #[doc(hidden)]
+                    static #ops: rtic::export::DelayOps<super::super::#name> =
+                        rtic::export::DelayOps {
+                            now,
+                            enqueue: delay_enqueue,
+                            cancel: delay_cancel,
+                        };
+
+                    unsafe fn delay_enqueue(
+                        instant: rtic::time::Instant<super::super::#name>,
+                        waker: *mut Option<core::task::Waker>,
+                    ) -> Option<rtic::export::Key> {
+                        #[allow(unused_imports)]
+                        use super::super::#rt_err;
+
+                        rtic::export::interrupt::free(|_| {
+                            let tq = &mut *super::super::#tq.get_mut_unchecked().as_mut_ptr();
+
+                            if tq.0.is_full() {
+                                #fail
+                                return None;
+                            }
 
-            let default_monotonic = if monotonic.args.default {
-                quote!(pub use #name::now;)
+                            let nr = rtic::export::NotReady {
+                                instant,
+                                index: 0,
+                                task: super::super::#t::#delay(waker),
+                            };
+
+                            let key = tq.enqueue_unchecked(
+                                nr,
+                                || #enable_interrupt,
+                                || #pend,
+                                super::super::#ident.get_mut_unchecked().as_mut(),
+                            );
+                            #record
+
+                            Some(key)
+                        })
+                    }
+
+                    fn delay_cancel(key: rtic::export::Key) {
+                        rtic::export::interrupt::free(|_| unsafe {
+                            (&mut *super::super::#tq.get_mut_unchecked().as_mut_ptr()).cancel(key);
+                        })
+                    }
+                }
             } else {
                 quote!()
             };
@@ -127,6 +259,7 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
                 #[doc = #doc]
                 #[allow(non_snake_case)]
                 pub mod #name {
+                    #id
 
                     /// Read the current time from this monotonic
                     pub fn now() -> rtic::time::Instant<super::super::#name> {
@@ -144,6 +277,54 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
                             }
                         })
                     }
+
+                    /// Returns the instant of the next task or delay scheduled on this monotonic,
+                    /// if any
+                    pub fn next_deadline() -> Option<rtic::time::Instant<super::super::#name>> {
+                        rtic::export::interrupt::free(|_| unsafe {
+                            (&*super::super::#tq.get_unchecked().as_ptr())
+                                .0
+                                .peek()
+                                .map(|nr| nr.instant)
+                        })
+                    }
+
+                    /// Enters a low-power sleep, meant to be called from `#[idle]`
+                    ///
+                    /// `sleep` runs with interrupts disabled and receives the monotonic and the
+                    /// next deadline, so no task can be scheduled in between. It should arm a
+                    /// wake-up source for the deadline and sleep (`wfi` still returns on a pending
+                    /// interrupt); if the monotonic stops counting in that sleep mode, `sleep`
+                    /// must also compensate it for the time slept. Afterwards the timer queue is
+                    /// processed so that tasks which expired in the meantime are released.
+                    pub fn sleep<F, R>(sleep: F) -> R
+                    where
+                        F: FnOnce(
+                            &mut super::super::#name,
+                            Option<rtic::time::Instant<super::super::#name>>,
+                        ) -> R,
+                    {
+                        #[allow(unused_imports)]
+                        use super::super::#rt_err;
+
+                        rtic::export::interrupt::free(|_| unsafe {
+                            let deadline = next_deadline();
+
+                            let r = if let Some(m) = super::super::#ident.get_mut_unchecked() {
+                                sleep(m, deadline)
+                            } else {
+                                panic!(#panic_str);
+                            };
+
+                            if deadline.is_some() {
+                                #pend;
+                            }
+
+                            r
+                        })
+                    }
+
+                    #delays
                 }
             }
         })
@@ -161,6 +342,20 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
     } else {
         quote!()
     };
+    let task_names = if extra.tracer.is_some() {
+        let names = util::traced_tasks(app)
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let n = names.len();
+
+        quote!(
+            /// Names of the tasks, indexed by the IDs passed to the `rtic::Tracer` hooks
+            pub const TASK_NAMES: [&str; #n] = [#(#names,)*];
+        )
+    } else {
+        quote!()
+    };
     let rt_err = util::rt_err_ident();
 
     quote!(
@@ -171,6 +366,10 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
 
             #monotonics
 
+            #mod_pools
+
+            #task_names
+
             #(#user_imports)*
 
             /// User code from within the module
@@ -196,6 +395,10 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
 
             #(#mod_app_resources)*
 
+            #(#mod_app_channels)*
+
+            #(#mod_app_pools)*
+
             #(#mod_app_hardware_tasks)*
 
             #(#mod_app_software_tasks)*
@@ -204,6 +407,8 @@ pub fn app(app: &App, analysis: &Analysis, extra: &Extra) -> TokenStream2 {
 
             #(#mod_app_timer_queue)*
 
+            #(#mod_app_stats)*
+
             #(#mains)*
         }
     )
@@ -1,11 +1,11 @@
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
-use crate::analyze::Analysis;
+use crate::{analyze::Analysis, check::Extra};
 use rtic_syntax::ast::App;
 
 /// Generates compile-time assertions that check that types implement the `Send` / `Sync` traits
-pub fn codegen(app: &App, analysis: &Analysis) -> Vec<TokenStream2> {
+pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream2> {
     let mut stmts = vec![];
 
     for ty in &analysis.send_types {
@@ -16,6 +16,12 @@ pub fn codegen(app: &App, analysis: &Analysis) -> Vec<TokenStream2> {
         stmts.push(quote!(rtic::export::assert_sync::<#ty>();));
     }
 
+    // Messages may cross priority levels
+    for channel in &extra.channels {
+        let ty = &channel.ty;
+        stmts.push(quote!(rtic::export::assert_send::<#ty>();));
+    }
+
     for (_, monotonic) in &app.monotonics {
         let ty = &monotonic.ty;
         stmts.push(quote!(rtic::export::assert_monotonic::<#ty>();));
@@ -0,0 +1,120 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use rtic_syntax::ast::App;
+use syn::{Attribute, Ident};
+
+use crate::{check::Extra, codegen::util};
+
+/// Generates the queues of the channels
+pub fn codegen(extra: &Extra) -> Vec<TokenStream2> {
+    let mut mod_app = vec![];
+
+    for channel in &extra.channels {
+        let ty = &channel.ty;
+        let n = util::capacity_typenum(channel.capacity.into(), true);
+        let queue = util::channel_ident(&channel.name);
+        let queue = util::mark_internal_ident(&queue);
+
+        mod_app.push(quote!(
+            #[doc(hidden)]
+            static #queue: rtic::RacyCell<rtic::export::ChannelQueue<#ty, #n>> =
+                rtic::RacyCell::new(rtic::export::Queue(unsafe {
+                    rtic::export::iQueue::u8_sc()
+                }));
+        ));
+    }
+
+    mod_app
+}
+
+/// Generates the `${task}Senders` (or `${task}Receivers`) struct of a task, which holds its ends
+/// of the `channels`
+pub fn ends(
+    task: &Ident,
+    cfgs: &[Attribute],
+    receivers: bool,
+    channels: &[Ident],
+    app: &App,
+    extra: &Extra,
+) -> (TokenStream2, Ident) {
+    let device = &extra.device;
+    let (ident, doc) = if receivers {
+        (
+            util::internal_task_ident(task, "Receivers"),
+            "Channels this task receives from",
+        )
+    } else {
+        (
+            util::internal_task_ident(task, "Senders"),
+            "Channels this task sends to",
+        )
+    };
+
+    let mut fields = vec![];
+    let mut values = vec![];
+    for name in channels {
+        let channel = extra
+            .channels
+            .iter()
+            .find(|channel| channel.name == *name)
+            .expect("UNREACHABLE");
+        let ty = &channel.ty;
+        let n = util::capacity_typenum(channel.capacity.into(), true);
+        let queue = util::channel_ident(name);
+        let queue = util::mark_internal_ident(&queue);
+        let ceiling = util::channel_ceiling(app, extra, name);
+
+        if receivers {
+            fields.push(quote!(pub #name: rtic::Receiver<'a, #ty, #n>));
+            values.push(quote!(#name: rtic::Receiver::new(
+                #queue.get_mut(),
+                priority,
+                #ceiling,
+                #device::NVIC_PRIO_BITS,
+            )));
+        } else {
+            let capacity = channel.capacity;
+            let notify = if let Some(spawn) = &channel.spawn {
+                let spawn_unique = util::internal_task_ident(spawn, "spawn_unique");
+
+                // The receiver drains the channel, so a pending instance is enough
+                quote!(Some((|| {
+                    #spawn_unique().ok();
+                }) as fn()))
+            } else {
+                quote!(None)
+            };
+
+            fields.push(quote!(pub #name: rtic::Sender<'a, #ty, #n>));
+            values.push(quote!(#name: rtic::Sender::new(
+                #queue.get_mut(),
+                priority,
+                #capacity,
+                #ceiling,
+                #device::NVIC_PRIO_BITS,
+                #notify,
+            )));
+        }
+    }
+
+    let item = quote!(
+        #(#cfgs)*
+        #[allow(non_camel_case_types)]
+        #[doc = #doc]
+        pub struct #ident<'a> {
+            #(#fields,)*
+        }
+
+        #(#cfgs)*
+        impl<'a> #ident<'a> {
+            #[inline(always)]
+            pub unsafe fn new(priority: &'a rtic::export::Priority) -> Self {
+                #ident {
+                    #(#values,)*
+                }
+            }
+        }
+    );
+
+    (item, ident)
+}
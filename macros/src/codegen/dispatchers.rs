@@ -5,10 +5,11 @@ use rtic_syntax::ast::App;
 use crate::{analyze::Analysis, check::Extra, codegen::util};
 
 /// Generates task dispatchers
-pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStream2> {
+pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream2> {
     let mut items = vec![];
 
     let interrupts = &analysis.interrupts;
+    let mut multiplexed = vec![];
 
     for (&level, channel) in &analysis.channels {
         let mut stmts = vec![];
@@ -42,16 +43,19 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
             }
         ));
 
-        let n = util::capacity_typenum(channel.capacity, true);
+        let edf = analysis.edf.contains(&level);
         let rq = util::rq_ident(level);
         let rq = util::mark_internal_ident(&rq);
-        let (rq_ty, rq_expr) = {
-            (
-                quote!(rtic::export::SCRQ<#t, #n>),
-                quote!(rtic::export::Queue(unsafe {
-                    rtic::export::iQueue::u8_sc()
-                })),
-            )
+        // Initialized in `pre_init`
+        let n = util::capacity_typenum(channel.capacity.into(), false);
+        let rq_ty = if edf {
+            // Sorted by deadline
+            let monotonic = util::default_monotonic(app).expect("RTIC-ICE: default monotonic");
+            let mono_type = &monotonic.ident;
+
+            quote!(rtic::export::EdfQueue<#mono_type, #t, #n>)
+        } else {
+            quote!(rtic::export::ReadyQueue<#t, #n>)
         };
 
         // For future use
@@ -61,9 +65,25 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
         // );
         items.push(quote!(
             #[doc(hidden)]
-            static #rq: rtic::RacyCell<#rq_ty> = rtic::RacyCell::new(#rq_expr);
+            static #rq: rtic::RacyCell<core::mem::MaybeUninit<#rq_ty>> =
+                rtic::RacyCell::new(core::mem::MaybeUninit::uninit());
         ));
 
+        let async_tasks = channel
+            .tasks
+            .iter()
+            .filter(|name| extra.tasks[*name].is_async)
+            .collect::<Vec<_>>();
+        let priority_static = util::priority_ident(level);
+        let priority_static = util::mark_internal_ident(&priority_static);
+        if !async_tasks.is_empty() {
+            items.push(quote!(
+                #[doc(hidden)]
+                static #priority_static: rtic::RacyCell<rtic::export::Priority> =
+                    rtic::RacyCell::new(unsafe { rtic::export::Priority::new(#level) });
+            ));
+        }
+
         let arms = channel
             .tasks
             .iter()
@@ -76,12 +96,142 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
                 let inputs = util::mark_internal_ident(&inputs);
                 let (_, tupled, pats, _) = util::regroup_inputs(&task.inputs);
 
+                if extra.tasks[name].is_async {
+                    let futures = util::futures_ident(name);
+                    let futures = util::mark_internal_ident(&futures);
+                    let poll = util::trace(
+                        app,
+                        extra,
+                        name,
+                        quote!(if slot.poll_if_woken() {
+                            #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
+                        }),
+                    );
+
+                    // Runs up to the first `.await`; the slot is freed once the future completes
+                    return quote!(
+                        #(#cfgs)*
+                        #t::#name => {
+                            let #tupled =
+                                #inputs
+                                .get_unchecked()
+                                .get_unchecked(usize::from(index))
+                                .as_ptr()
+                                .read();
+                            let slot = #futures.get_unchecked().get_unchecked(usize::from(index));
+                            slot.spawn(#name(
+                                #name::Context::new(#priority_static.get_unchecked())
+                                #(,#pats)*
+                            ));
+
+                            #poll
+                        }
+                    );
+                }
+
                 let locals_new = if task.locals.is_empty() {
                     quote!()
                 } else {
                     quote!(#name::Locals::new(),)
                 };
 
+                if extra.tasks[name].by_ref {
+                    let run = util::measure_budget(
+                        app,
+                        extra,
+                        name,
+                        quote!(#name(
+                            #locals_new
+                            #name::Context::new(priority)
+                            #(,#pats)*
+                        )),
+                    );
+                    let run = util::trace(app, extra, name, run);
+
+                    // The message is lent to the task and dropped in place; only then is its slot
+                    // reused
+                    return quote!(
+                        #(#cfgs)*
+                        #t::#name => {
+                            let slot = #inputs
+                                .get_mut_unchecked()
+                                .get_unchecked_mut(usize::from(index))
+                                .as_mut_ptr();
+                            let #tupled = &mut *slot;
+                            let priority = &rtic::export::Priority::new(PRIORITY);
+                            {
+                                #run
+                            }
+                            core::ptr::drop_in_place(slot);
+                            #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
+                        }
+                    );
+                }
+
+                let release = if let Some(period) = &extra.tasks[name].period {
+                    // Periodic tasks keep their slot and are re-enqueued into the timer queue,
+                    // relative to the instant they were released at so that no drift accumulates
+                    let monotonic =
+                        util::default_monotonic(app).expect("RTIC-ICE: default monotonic");
+                    let monotonic_name = monotonic.ident.to_string();
+                    let tq = util::tq_ident(&monotonic_name);
+                    let tq_internal = util::mark_internal_ident(&tq);
+                    let record_tq = util::stats_record(
+                        &tq,
+                        quote!((&*#tq_internal.get_unchecked().as_ptr()).0.len()),
+                    );
+                    let tq = tq_internal;
+                    let m_ident = util::monotonic_ident(&monotonic_name);
+                    let m_ident = util::mark_internal_ident(&m_ident);
+                    let instants = util::monotonic_instants_ident(name, &monotonic.ident);
+                    let instants = util::mark_internal_ident(&instants);
+                    let periodic = util::periodic_ident(name);
+                    let periodic = util::mark_internal_ident(&periodic);
+                    let st = util::schedule_t_ident();
+                    let (enable_interrupt, pend) = util::monotonic_enable_and_pend(monotonic);
+
+                    quote!(
+                        rtic::export::interrupt::free(|_| match #periodic.get_mut_unchecked() {
+                            Some((slot, key)) if *slot == index => {
+                                let instant = #instants
+                                    .get_mut_unchecked()
+                                    .get_unchecked_mut(usize::from(index));
+                                let next = instant.as_ptr().read() + #period;
+                                instant.as_mut_ptr().write(next);
+
+                                let nr = rtic::export::NotReady {
+                                    instant: next,
+                                    index,
+                                    task: #st::#name,
+                                };
+
+                                *key = (&mut *#tq.get_mut_unchecked().as_mut_ptr()).enqueue_unchecked(
+                                    nr,
+                                    || #enable_interrupt,
+                                    || #pend,
+                                    #m_ident.get_mut_unchecked().as_mut());
+                                #record_tq
+                            }
+
+                            _ => #fq.get_mut_unchecked().split().0.enqueue_unchecked(index),
+                        });
+                    )
+                } else {
+                    quote!(#fq.get_mut_unchecked().split().0.enqueue_unchecked(index);)
+                };
+
+                let run = util::measure_budget(
+                    app,
+                    extra,
+                    name,
+                    quote!(#name(
+                        #locals_new
+                        #name::Context::new(priority)
+                        #(,#pats)*
+                    )),
+                );
+                let run = util::trace(app, extra, name, run);
+
                 quote!(
                     #(#cfgs)*
                     #t::#name => {
@@ -91,26 +241,60 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
                             .get_unchecked(usize::from(index))
                             .as_ptr()
                             .read();
-                        #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
+                        #release
                         let priority = &rtic::export::Priority::new(PRIORITY);
-                        #name(
-                            #locals_new
-                            #name::Context::new(priority)
-                            #(,#pats)*
-                        )
+                        #run
                     }
                 )
             })
             .collect::<Vec<_>>();
 
         stmts.push(quote!(
-            while let Some((task, index)) = #rq.get_mut_unchecked().split().1.dequeue() {
+            // Spawners and `cancel` may change any entry of the queue
+            while let Some((task, index)) = rtic::export::interrupt::free(|_| {
+                (&mut *#rq.get_mut_unchecked().as_mut_ptr()).dequeue()
+            }) {
                 match task {
                     #(#arms)*
                 }
             }
         ));
 
+        for name in async_tasks {
+            let cfgs = &app.software_tasks[name].cfgs;
+            let fq = util::fq_ident(name);
+            let fq = util::mark_internal_ident(&fq);
+            let futures = util::futures_ident(name);
+            let futures = util::mark_internal_ident(&futures);
+
+            let poll = quote!(if slot.poll_if_woken() {
+                #fq.get_mut_unchecked().split().0.enqueue_unchecked(index as u8);
+            });
+            // The tracer is only told about the futures that are actually polled
+            let poll = if extra.tracer.is_some() {
+                let poll = util::trace(app, extra, name, poll);
+
+                quote!(if slot.is_woken() {
+                    #poll
+                })
+            } else {
+                poll
+            };
+
+            stmts.push(quote!(
+                // Futures that were woken since they were last polled
+                #(#cfgs)*
+                for (index, slot) in #futures.get_unchecked().iter().enumerate() {
+                    #poll
+                }
+            ));
+        }
+
+        if analysis.multiplexed.contains(&level) {
+            multiplexed.push((level, stmts));
+            continue;
+        }
+
         let doc = format!("Interrupt handler to dispatch tasks at priority {}", level);
         let interrupt = util::suffixed(&interrupts[&level].0.to_string());
         let attribute = &interrupts[&level].1.attrs;
@@ -130,5 +314,74 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
         ));
     }
 
+    // One handler serves all the multiplexed levels, highest pending level first
+    if let Some(&(highest, _)) = multiplexed.last() {
+        let multiplex = util::mark_internal_name("MULTIPLEX");
+        let pend_multiplexed = util::mark_internal_name("pend_multiplexed");
+        let interrupt = util::suffixed(&interrupts[&highest].0.to_string());
+        let attribute = &interrupts[&highest].1.attrs;
+        let pend = util::pend_interrupt(analysis, extra, highest);
+        let set_priority = util::set_dispatcher_priority(extra, &interrupt, quote!(level));
+
+        items.push(quote!(
+            #[doc(hidden)]
+            static #multiplex: rtic::export::Multiplexer = rtic::export::Multiplexer::new();
+
+            /// Pends the multiplexed dispatcher on behalf of priority `level`
+            #[doc(hidden)]
+            fn #pend_multiplexed(level: u8) {
+                rtic::export::interrupt::free(|cs| {
+                    // An idle dispatcher is entered at its highest pending level
+                    if let Some(level) = #multiplex.pend(level, cs) {
+                        unsafe { #set_priority };
+                    }
+                });
+
+                #pend;
+            }
+        ));
+
+        let arms = multiplexed.iter().map(|(level, stmts)| {
+            quote!(
+                #level => {
+                    /// The priority of the level being dispatched
+                    const PRIORITY: u8 = #level;
+
+                    rtic::export::run(PRIORITY, || {
+                        #(#stmts)*
+                    });
+                }
+            )
+        });
+
+        let levels = multiplexed
+            .iter()
+            .map(|(level, _)| level.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let doc = format!(
+            "Interrupt handler to dispatch tasks at priorities {}, multiplexed",
+            levels
+        );
+        items.push(quote!(
+            #[allow(non_snake_case)]
+            #[doc = #doc]
+            #[no_mangle]
+            #(#attribute)*
+            unsafe fn #interrupt() {
+                while let Some(level) = rtic::export::interrupt::free(|cs| #multiplex.next(cs)) {
+                    // The handler runs at the priority of the level it dispatches, so only tasks
+                    // of higher priority preempt it
+                    #set_priority;
+
+                    match level {
+                        #(#arms)*
+                        _ => {}
+                    }
+                }
+            }
+        ));
+    }
+
     items
 }
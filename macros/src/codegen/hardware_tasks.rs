@@ -5,7 +5,7 @@ use rtic_syntax::{ast::App, Context};
 use crate::{
     analyze::Analysis,
     check::Extra,
-    codegen::{locals, module, resources_struct},
+    codegen::{locals, module, resources_struct, util},
 };
 
 /// Generate support code for hardware tasks (`#[exception]`s and `#[interrupt]`s)
@@ -39,6 +39,16 @@ pub fn codegen(
         let priority = task.args.priority;
         let cfgs = &task.cfgs;
         let attrs = &task.attrs;
+        let run = util::measure_budget(
+            app,
+            extra,
+            name,
+            quote!(#name(
+                #locals_new
+                #name::Context::new(&rtic::export::Priority::new(PRIORITY))
+            )),
+        );
+        let run = util::trace(app, extra, name, run);
 
         mod_app.push(quote!(
             #[allow(non_snake_case)]
@@ -49,14 +59,22 @@ pub fn codegen(
                 const PRIORITY: u8 = #priority;
 
                 rtic::export::run(PRIORITY, || {
-                    #name(
-                        #locals_new
-                        #name::Context::new(&rtic::export::Priority::new(PRIORITY))
-                    )
+                    #run
                 });
             }
         ));
 
+        if extra.tasks[name].has_budget() {
+            let stats = util::execution_time_ident(name);
+            let stats = util::mark_internal_ident(&stats);
+            mod_app.push(quote!(
+                #(#cfgs)*
+                #[doc(hidden)]
+                static #stats: rtic::RacyCell<rtic::ExecutionTime> =
+                    rtic::RacyCell::new(rtic::ExecutionTime::new());
+            ));
+        }
+
         let mut needs_lt = false;
 
         // `${task}Resources`
@@ -5,7 +5,7 @@ use rtic_syntax::{ast::App, Context};
 use crate::{
     analyze::Analysis,
     check::Extra,
-    codegen::{locals, module, resources_struct},
+    codegen::{locals, module, resources_struct, util},
 };
 
 /// Generates support code for `#[idle]` functions
@@ -75,10 +75,19 @@ pub fn codegen(
         ));
 
         let locals_new = locals_new.iter();
-        let call_idle = quote!(#name(
-            #(#locals_new,)*
-            #name::Context::new(&rtic::export::Priority::new(0))
-        ));
+        // `idle` never returns, so the tracer is only told that it starts
+        let enter = extra.tracer.as_ref().map(|tracer| {
+            let id = util::task_id(app, name);
+
+            quote!(<#tracer as rtic::Tracer>::task_enter(#id);)
+        });
+        let call_idle = quote!(
+            #enter
+            #name(
+                #(#locals_new,)*
+                #name::Context::new(&rtic::export::Priority::new(0))
+            )
+        );
 
         (mod_app, root_idle, user_idle, call_idle)
     } else {
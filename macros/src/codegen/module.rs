@@ -1,4 +1,9 @@
-use crate::{analyze::Analysis, check::Extra, codegen::util};
+use crate::{
+    analyze::Analysis,
+    check::Extra,
+    codegen::{channels, util},
+    syntax::Overflow,
+};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use rtic_syntax::{ast::App, Context};
@@ -92,6 +97,47 @@ pub fn codegen(
         values.push(quote!(resources: #name::Resources::new(#priority)));
     }
 
+    // Ends of the channels the task (or `#[idle]`) uses
+    if let Some(args) = extra.tasks.get(name) {
+        let cfgs = match ctxt {
+            Context::HardwareTask(t) => app.hardware_tasks[t].cfgs.clone(),
+            Context::SoftwareTask(t) => app.software_tasks[t].cfgs.clone(),
+            _ => vec![],
+        };
+
+        for (receivers, names) in [(false, &args.senders), (true, &args.receivers)].iter() {
+            if names.is_empty() {
+                continue;
+            }
+
+            let (item, ident) = channels::ends(name, &cfgs, *receivers, names, app, extra);
+            items.push(item);
+
+            lt = Some(quote!('a));
+            if *receivers {
+                module_items.push(quote!(
+                    #[doc(inline)]
+                    pub use super::#ident as Receivers;
+                ));
+                fields.push(quote!(
+                    /// Channels this task receives from
+                    pub receivers: #name::Receivers<'a>
+                ));
+                values.push(quote!(receivers: #name::Receivers::new(priority)));
+            } else {
+                module_items.push(quote!(
+                    #[doc(inline)]
+                    pub use super::#ident as Senders;
+                ));
+                fields.push(quote!(
+                    /// Channels this task sends to
+                    pub senders: #name::Senders<'a>
+                ));
+                values.push(quote!(senders: #name::Senders::new(priority)));
+            }
+        }
+    }
+
     if let Context::Init = ctxt {
         let late_fields = analysis
             .late_resources
@@ -202,13 +248,22 @@ pub fn codegen(
         pub use super::#internal_context_name as Context;
     ));
 
+    if extra.tracer.is_some() && !ctxt.is_init() {
+        let id = util::task_id(app, name);
+
+        module_items.push(quote!(
+            #(#cfgs)*
+            /// ID of the task, as passed to the `rtic::Tracer` hooks
+            pub const ID: u16 = #id;
+        ));
+    }
+
     // not sure if this is the right way, maybe its backwards,
     // that spawn_module should put in in root
 
     if let Context::SoftwareTask(..) = ctxt {
         let spawnee = &app.software_tasks[name];
         let priority = spawnee.args.priority;
-        let t = util::spawn_t_ident(priority);
         let cfgs = &spawnee.cfgs;
         // Store a copy of the task cfgs
         task_cfgs = cfgs.clone();
@@ -217,51 +272,313 @@ pub fn codegen(
         let tupled = &tupled;
         let fq = util::fq_ident(name);
         let fq = util::mark_internal_ident(&fq);
-        let rq = util::rq_ident(priority);
-        let rq = util::mark_internal_ident(&rq);
+        let fq_dequeue = util::fq_dequeue(name, spawnee.args.capacity);
         let inputs = util::inputs_ident(name);
         let inputs = util::mark_internal_ident(&inputs);
 
-        let device = &extra.device;
-        let enum_ = util::interrupt_ident();
-        let interrupt = &analysis
-            .interrupts
-            .get(&priority)
-            .expect("RTIC-ICE: interrupt identifer not found")
-            .0;
+        let pend = util::pend_dispatcher(analysis, extra, priority);
 
         let internal_spawn_ident = util::internal_task_ident(name, "spawn");
+        let internal_ready_handle_ident = util::internal_task_ident(name, "ReadyHandle");
+        let rq = util::rq_ident(priority);
+        let rq = util::mark_internal_ident(&rq);
+        let enqueue = util::rq_enqueue(app, analysis, extra, priority, name, quote!(deadline));
+        let edf_mono = if analysis.edf.contains(&priority) {
+            util::default_monotonic(app).map(|monotonic| &monotonic.ident)
+        } else {
+            None
+        };
+
+        // On EDF levels the spawn with an explicit deadline does the work
+        let (spawn_ident, deadline_arg, doc) = if let Some(m) = edf_mono {
+            (
+                util::internal_task_ident(name, "spawn_with_deadline"),
+                Some(quote!(deadline: rtic::time::Instant<#m>,)),
+                "Spawns the task directly, dispatched before the tasks with a later deadline",
+            )
+        } else {
+            (
+                internal_spawn_ident.clone(),
+                None,
+                "Spawns the task directly",
+            )
+        };
+
+        // What to do when all the slots are in use
+        let spawn_t = util::spawn_t_ident(priority);
+        let overflow = match extra.tasks[name].overflow {
+            Some(Overflow::DropOldest) => quote!(
+                match rtic::export::interrupt::free(|_| {
+                    let rq = &mut *#rq.get_mut_unchecked().as_mut_ptr();
+
+                    // Evict the first message that's still waiting, its slot is taken over; that's
+                    // the oldest one, or the one with the earliest deadline on EDF levels
+                    if let Some((_task, index)) = rq
+                        .0
+                        .find_key(|r| matches!(r.task, #spawn_t::#name))
+                        .and_then(|key| rq.cancel(key))
+                    {
+                        let slot = #inputs.get_mut_unchecked().get_unchecked_mut(usize::from(index));
+                        core::ptr::drop_in_place(slot.as_mut_ptr());
+                        slot.as_mut_ptr().write(input);
+
+                        Ok(#enqueue)
+                    } else {
+                        Err(input)
+                    }
+                }) {
+                    Ok(key) => key,
+                    Err(input) => return Err(rtic::SpawnError::Full(input)),
+                }
+            ),
+            Some(Overflow::ReplaceLatest) => quote!(
+                match rtic::export::interrupt::free(|_| {
+                    let rq = &mut *#rq.get_mut_unchecked().as_mut_ptr();
+
+                    // Overwrite the newest message that's still waiting, it keeps its place
+                    if let Some((key, index)) = rq
+                        .0
+                        .rfind_key(|r| matches!(r.task, #spawn_t::#name))
+                        .and_then(|key| rq.0.get(key).map(|r| (key, r.index)))
+                    {
+                        let slot = #inputs.get_mut_unchecked().get_unchecked_mut(usize::from(index));
+                        core::ptr::drop_in_place(slot.as_mut_ptr());
+                        slot.as_mut_ptr().write(input);
+
+                        Ok(key)
+                    } else {
+                        Err(input)
+                    }
+                }) {
+                    Ok(key) => key,
+                    Err(input) => return Err(rtic::SpawnError::Full(input)),
+                }
+            ),
+            Some(Overflow::Error) | None => quote!(
+                return Err(rtic::SpawnError::Full(input));
+            ),
+        };
 
         // Spawn caller
         items.push(quote!(
 
         #(#cfgs)*
-        /// Spawns the task directly
-        pub fn #internal_spawn_ident(#(#args,)*) -> Result<(), #ty> {
+        #[doc = #doc]
+        pub fn #spawn_ident(#deadline_arg #(#args,)*) -> Result<#name::ReadyHandle, rtic::SpawnError<#ty>> {
             let input = #tupled;
 
             unsafe {
-                if let Some(index) = rtic::export::interrupt::free(|_| #fq.get_mut_unchecked().dequeue()) {
+                let key = if let Some(index) = rtic::export::interrupt::free(|_| #fq_dequeue) {
                     #inputs
                         .get_mut_unchecked()
                         .get_unchecked_mut(usize::from(index))
                         .as_mut_ptr()
                         .write(input);
 
-                    rtic::export::interrupt::free(|_| {
-                        #rq.get_mut_unchecked().enqueue_unchecked((#t::#name, index));
-                    });
+                    rtic::export::interrupt::free(|_| #enqueue)
+                } else {
+                    #overflow
+                };
 
-                    rtic::pend(#device::#enum_::#interrupt);
+                #pend;
 
-                    Ok(())
-                } else {
-                    Err(input)
-                }
+                Ok(#name::ReadyHandle { key })
             }
 
         }));
 
+        items.push(quote!(
+            #(#cfgs)*
+            /// Handle to an instance spawned with `spawn`, valid until it's dispatched
+            #[derive(Debug)]
+            pub struct #internal_ready_handle_ident {
+                #[doc(hidden)]
+                key: rtic::export::ReadyKey,
+            }
+
+            #(#cfgs)*
+            impl #internal_ready_handle_ident {
+                /// Takes the instance out of the ready queue and returns its message
+                pub fn cancel(self) -> Result<#ty, rtic::SpawnError<()>> {
+                    rtic::export::interrupt::free(|_| unsafe {
+                        let rq = &mut *#rq.get_mut_unchecked().as_mut_ptr();
+                        if let Some((_task, index)) = rq.cancel(self.key) {
+                            // Get the message
+                            let msg = #inputs
+                                .get_unchecked()
+                                .get_unchecked(usize::from(index))
+                                .as_ptr()
+                                .read();
+                            // Return the index to the free queue
+                            #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
+
+                            Ok(msg)
+                        } else {
+                            Err(rtic::SpawnError::AlreadyDispatched)
+                        }
+                    })
+                }
+            }
+        ));
+
+        module_items.push(quote!(
+            #(#cfgs)*
+            pub use super::#internal_ready_handle_ident as ReadyHandle;
+        ));
+
+        // Coalescing spawn of wake-up tasks
+        if spawnee.inputs.is_empty() {
+            let internal_spawn_unique_ident = util::internal_task_ident(name, "spawn_unique");
+            let unique = util::unique_ident(name);
+            let unique = util::mark_internal_ident(&unique);
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Spawns the task unless the instance spawned by the previous call hasn't
+                /// started running yet
+                ///
+                /// Returns `Ok(false)` if it was still pending; no further slot is taken then
+                pub fn #internal_spawn_unique_ident() -> Result<bool, rtic::SpawnError<()>> {
+                    rtic::export::interrupt::free(|_| unsafe {
+                        let rq = &*#rq.get_unchecked().as_ptr();
+
+                        if let Some(key) = *#unique.get_unchecked() {
+                            if rq.0.get(key).is_some() {
+                                return Ok(false);
+                            }
+                        }
+
+                        let handle = #internal_spawn_ident()?;
+                        *#unique.get_mut_unchecked() = Some(handle.key);
+
+                        Ok(true)
+                    })
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_spawn_unique_ident as spawn_unique;
+            ));
+        }
+
+        // In-place construction of the message
+        if !spawnee.inputs.is_empty() {
+            let internal_spawn_with_ident = util::internal_task_ident(name, "spawn_with");
+            let guard = util::mark_internal_name("SlotGuard");
+            let deadline = if let Some(m) = edf_mono {
+                let m_ident = util::monotonic_ident(&m.to_string());
+                let m_ident = util::mark_internal_ident(&m_ident);
+
+                quote!(
+                    let deadline = if rtic::export::interrupt::free(|_| #m_ident.get_unchecked().is_none()) {
+                        rtic::time::Instant::new(0)
+                    } else {
+                        monotonics::#m::now()
+                    };
+                )
+            } else {
+                quote!()
+            };
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Spawns the task with a message that `init` writes straight into its slot
+                ///
+                /// `init` isn't called when all the slots are in use; the `overflow` policy doesn't
+                /// apply here. If `init` panics the slot is given back.
+                ///
+                /// # Safety
+                ///
+                /// `init` must initialize the message in the slot it's given
+                pub unsafe fn #internal_spawn_with_ident<F>(init: F) -> Result<#name::ReadyHandle, rtic::SpawnError<()>>
+                where
+                    F: FnOnce(&mut core::mem::MaybeUninit<#ty>),
+                {
+                    let index = match rtic::export::interrupt::free(|_| #fq_dequeue) {
+                        Some(index) => index,
+                        None => return Err(rtic::SpawnError::Full(())),
+                    };
+
+                    // Returns the index to the free queue unless it's forgotten
+                    #[allow(non_camel_case_types)]
+                    struct #guard(u8);
+
+                    impl Drop for #guard {
+                        fn drop(&mut self) {
+                            rtic::export::interrupt::free(|_| unsafe {
+                                #fq.get_mut_unchecked().split().0.enqueue_unchecked(self.0);
+                            });
+                        }
+                    }
+
+                    let guard = #guard(index);
+                    init(#inputs.get_mut_unchecked().get_unchecked_mut(usize::from(index)));
+                    core::mem::forget(guard);
+
+                    #deadline
+                    let key = rtic::export::interrupt::free(|_| #enqueue);
+
+                    #pend;
+
+                    Ok(#name::ReadyHandle { key })
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_spawn_with_ident as spawn_with;
+            ));
+        }
+
+        // Allocation and spawn in one go, for tasks that take a `Box` of a `#[pool]`
+        if let Some(pooled) = util::pooled_message(extra, &spawnee.inputs) {
+            let internal_spawn_boxed_ident = util::internal_task_ident(name, "spawn_boxed");
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Moves `value` into a block of its `#[pool]` and spawns the task with it
+                ///
+                /// Fails with `SpawnError::PoolExhausted` when the pool has no free block.
+                pub fn #internal_spawn_boxed_ident(value: #pooled) -> Result<#name::ReadyHandle, rtic::SpawnError<#pooled>> {
+                    match pools::alloc(value) {
+                        Ok(boxed) => #internal_spawn_ident(boxed).map_err(|e| e.map(rtic::Box::into_inner)),
+                        Err(value) => Err(rtic::SpawnError::PoolExhausted(value)),
+                    }
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_spawn_boxed_ident as spawn_boxed;
+            ));
+        }
+
+        if let Some(m) = edf_mono {
+            let m_ident = util::monotonic_ident(&m.to_string());
+            let m_ident = util::mark_internal_ident(&m_ident);
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Spawns the task directly, with the current time as its deadline
+                pub fn #internal_spawn_ident(#(#args,)*) -> Result<#name::ReadyHandle, rtic::SpawnError<#ty>> {
+                    // Tasks spawned from `init` share the earliest deadline
+                    let deadline = if rtic::export::interrupt::free(|_| unsafe { #m_ident.get_unchecked().is_none() }) {
+                        rtic::time::Instant::new(0)
+                    } else {
+                        monotonics::#m::now()
+                    };
+
+                    #spawn_ident(deadline, #(#untupled,)*)
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#spawn_ident as spawn_with_deadline;
+            ));
+        }
+
         module_items.push(quote!(
             #(#cfgs)*
             pub use super::#internal_spawn_ident as spawn;
@@ -274,30 +591,14 @@ pub fn codegen(
             let monotonic_name = monotonic.ident.to_string();
 
             let tq = util::tq_ident(&monotonic.ident.to_string());
+            let record_tq = util::stats_record(&tq, quote!(tq.0.len()));
             let tq = util::mark_internal_ident(&tq);
             let t = util::schedule_t_ident();
             let m = &monotonic.ident;
             let mono_type = &monotonic.ident;
             let m_ident = util::monotonic_ident(&monotonic_name);
             let m_ident = util::mark_internal_ident(&m_ident);
-            let m_isr = &monotonic.args.binds;
-            let enum_ = util::interrupt_ident();
-
-            let (enable_interrupt, pend) = if &*m_isr.to_string() == "SysTick" {
-                (
-                    quote!(core::mem::transmute::<_, cortex_m::peripheral::SYST>(())
-                        .enable_interrupt()),
-                    quote!(cortex_m::peripheral::SCB::set_pendst()),
-                )
-            } else {
-                let rt_err = util::rt_err_ident();
-                (
-                    quote!(rtic::export::NVIC::unmask(#rt_err::#enum_::#m_isr)),
-                    quote!(rtic::pend(#rt_err::#enum_::#m_isr)),
-                )
-            };
-
-            let tq_marker = util::mark_internal_ident(&util::timer_queue_marker_ident());
+            let (enable_interrupt, pend) = util::monotonic_enable_and_pend(monotonic);
 
             // For future use
             // let doc = format!(" RTIC internal: {}:{}", file!(), line!());
@@ -307,11 +608,20 @@ pub fn codegen(
             let internal_spawn_at_ident = util::internal_monotonics_ident(name, m, "spawn_at");
             let internal_spawn_after_ident =
                 util::internal_monotonics_ident(name, m, "spawn_after");
+            let internal_spawn_or_reschedule_after_ident =
+                util::internal_monotonics_ident(name, m, "spawn_or_reschedule_after");
+            let outstanding = util::monotonic_outstanding_ident(name, m);
+            let outstanding = util::mark_internal_ident(&outstanding);
+            let cancelled = util::monotonic_cancelled_ident(name, m);
+            let cancelled = util::mark_internal_ident(&cancelled);
+            let released = util::monotonic_released_ident(name, m);
+            let released = util::mark_internal_ident(&released);
 
             if monotonic.args.default {
                 module_items.push(quote!(
                     pub use #m::spawn_after;
                     pub use #m::spawn_at;
+                    pub use #m::spawn_or_reschedule_after;
                     pub use #m::SpawnHandle;
                 ));
             }
@@ -319,21 +629,36 @@ pub fn codegen(
                 pub mod #m {
                     pub use super::super::#internal_spawn_after_ident as spawn_after;
                     pub use super::super::#internal_spawn_at_ident as spawn_at;
+                    pub use super::super::#internal_spawn_or_reschedule_after_ident as spawn_or_reschedule_after;
                     pub use super::super::#internal_spawn_handle_ident as SpawnHandle;
                 }
             ));
 
             items.push(quote!(
+                #[derive(Debug)]
                 pub struct #internal_spawn_handle_ident {
                     #[doc(hidden)]
-                    marker: u32,
+                    key: rtic::export::Key,
+                    #[doc(hidden)]
+                    index: u8,
                 }
 
                 impl #internal_spawn_handle_ident {
-                    pub fn cancel(self) -> Result<#ty, ()> {
+                    pub fn cancel(self) -> Result<#ty, rtic::SpawnError<()>> {
                         rtic::export::interrupt::free(|_| unsafe {
                             let tq = &mut *#tq.get_mut_unchecked().as_mut_ptr();
-                            if let Some((_task, index)) = tq.cancel_marker(self.marker) {
+                            let rq = &mut *#rq.get_mut_unchecked().as_mut_ptr();
+
+                            // Once released the instance can still be taken out of the ready queue
+                            let ready = match *#released.get_unchecked().get_unchecked(usize::from(self.index)) {
+                                Some((key, ready)) if key == self.key => Some(ready),
+                                _ => None,
+                            };
+
+                            if let Some((_task, index)) = tq
+                                .cancel(self.key)
+                                .or_else(|| ready.and_then(|ready| rq.cancel(ready)))
+                            {
                                 // Get the message
                                 let msg = #inputs
                                     .get_unchecked()
@@ -343,43 +668,125 @@ pub fn codegen(
                                 // Return the index to the free queue
                                 #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
 
+                                // Other handles to this instance are stale from now on
+                                *#cancelled
+                                    .get_mut_unchecked()
+                                    .get_unchecked_mut(usize::from(index)) = Some(self.key);
+
                                 Ok(msg)
                             } else {
-                                Err(())
+                                Err(self.gone())
                             }
                         })
                     }
 
+                    /// Moves the instance to `duration` from now, see `reschedule_at`
                     #[inline]
-                    pub fn reschedule_after<D>(self, duration: D) -> Result<Self, ()>
+                    pub fn reschedule_after<D>(self, duration: D) -> Result<Self, rtic::SpawnError<Self>>
                         where D: rtic::time::duration::Duration + rtic::time::fixed_point::FixedPoint,
                                  D::T: Into<<#mono_type as rtic::time::Clock>::T>,
                     {
+                        if rtic::export::interrupt::free(|_| unsafe { #m_ident.get_unchecked().is_none() }) {
+                            return Err(rtic::SpawnError::MonotonicNotReady(self));
+                        }
+
                         self.reschedule_at(monotonics::#m::now() + duration)
                     }
 
-                    pub fn reschedule_at(self, instant: rtic::time::Instant<#mono_type>) -> Result<Self, ()>
+                    /// Moves the instance to `instant`; the handle stays valid
+                    ///
+                    /// The entry is found in O(1) but re-sorted into the timer queue, which is
+                    /// O(n) in its length (O(log n) with the `tq-heap` feature).
+                    pub fn reschedule_at(self, instant: rtic::time::Instant<#mono_type>) -> Result<Self, rtic::SpawnError<Self>>
                     {
                         rtic::export::interrupt::free(|_| unsafe {
-                            let marker = *#tq_marker.get_mut_unchecked();
-                            *#tq_marker.get_mut_unchecked() = #tq_marker.get_mut_unchecked().wrapping_add(1);
-
                             let tq = &mut *#tq.get_mut_unchecked().as_mut_ptr();
 
-                            tq.update_marker(self.marker, marker, instant, || #pend).map(|_| #name::#m::SpawnHandle { marker })
+                            if let Ok(index) = tq.update(self.key, instant, || #pend) {
+                                #instants
+                                    .get_mut_unchecked()
+                                    .get_unchecked_mut(usize::from(index))
+                                    .as_mut_ptr()
+                                    .write(instant);
+
+                                Ok(self)
+                            } else {
+                                Err(self.gone())
+                            }
                         })
                     }
+
+                    /// Why the instance of this handle is no longer in the timer queue
+                    ///
+                    /// Must be called in a critical section
+                    unsafe fn gone<T>(&self) -> rtic::SpawnError<T> {
+                        let cancelled = #cancelled
+                            .get_unchecked()
+                            .get_unchecked(usize::from(self.index));
+
+                        if *cancelled == Some(self.key) {
+                            rtic::SpawnError::Stale
+                        } else {
+                            rtic::SpawnError::AlreadyDispatched
+                        }
+                    }
+                }
+
+                #(#cfgs)*
+                /// Reschedules the pending instance spawned by this function, replacing its
+                /// message, or spawns a new instance if there's none
+                ///
+                /// When called in `#[init]` the duration is relative to the moment `#[init]` returns
+                pub fn #internal_spawn_or_reschedule_after_ident<D>(
+                    duration: D
+                    #(,#args)*
+                ) -> Result<#name::#m::SpawnHandle, rtic::SpawnError<#ty>>
+                    where D: rtic::time::duration::Duration + rtic::time::fixed_point::FixedPoint,
+                        D::T: Into<<#mono_type as rtic::time::Clock>::T>,
+                {
+                    rtic::export::interrupt::free(|_| unsafe {
+                        // Read the time in the same critical section that moves the instance
+                        let instant = if #m_ident.get_unchecked().is_none() {
+                            rtic::time::Instant::new(0)
+                        } else {
+                            monotonics::#m::now()
+                        } + duration;
+                        let tq = &mut *#tq.get_mut_unchecked().as_mut_ptr();
+
+                        if let Some(key) = *#outstanding.get_unchecked() {
+                            if let Ok(index) = tq.update(key, instant, || #pend) {
+                                let input = #inputs
+                                    .get_mut_unchecked()
+                                    .get_unchecked_mut(usize::from(index));
+                                core::ptr::drop_in_place(input.as_mut_ptr());
+                                input.as_mut_ptr().write(#tupled);
+
+                                #instants
+                                    .get_mut_unchecked()
+                                    .get_unchecked_mut(usize::from(index))
+                                    .as_mut_ptr()
+                                    .write(instant);
+
+                                return Ok(#name::#m::SpawnHandle { key, index });
+                            }
+                        }
+
+                        // The previous instance was dispatched or cancelled
+                        let handle = #internal_spawn_at_ident(instant #(,#untupled)*)?;
+                        *#outstanding.get_mut_unchecked() = Some(handle.key);
+
+                        Ok(handle)
+                    })
                 }
 
                 #(#cfgs)*
                 /// Spawns the task after a set duration relative to the current time
                 ///
-                /// This will use the time `Instant::new(0)` as baseline if called in `#[init]`,
-                /// so if you use a non-resetable timer use `spawn_at` when in `#[init]`
+                /// When called in `#[init]` the duration is relative to the moment `#[init]` returns
                 pub fn #internal_spawn_after_ident<D>(
                     duration: D
                     #(,#args)*
-                ) -> Result<#name::#m::SpawnHandle, #ty>
+                ) -> Result<#name::#m::SpawnHandle, rtic::SpawnError<#ty>>
                     where D: rtic::time::duration::Duration + rtic::time::fixed_point::FixedPoint,
                         D::T: Into<<#mono_type as rtic::time::Clock>::T>,
                 {
@@ -398,10 +805,10 @@ pub fn codegen(
                 pub fn #internal_spawn_at_ident(
                     instant: rtic::time::Instant<#mono_type>
                     #(,#args)*
-                ) -> Result<#name::#m::SpawnHandle, #ty> {
+                ) -> Result<#name::#m::SpawnHandle, rtic::SpawnError<#ty>> {
                     unsafe {
                         let input = #tupled;
-                        if let Some(index) = rtic::export::interrupt::free(|_| #fq.get_mut_unchecked().dequeue()) {
+                        if let Some(index) = rtic::export::interrupt::free(|_| #fq_dequeue) {
                             #inputs
                                 .get_mut_unchecked()
                                 .get_unchecked_mut(usize::from(index))
@@ -415,33 +822,174 @@ pub fn codegen(
                                 .write(instant);
 
                             rtic::export::interrupt::free(|_| {
-                                let marker = *#tq_marker.get_mut_unchecked();
                                 let nr = rtic::export::NotReady {
                                     instant,
                                     index,
                                     task: #t::#name,
-                                    marker,
                                 };
 
-                                *#tq_marker.get_mut_unchecked() = #tq_marker.get_mut_unchecked().wrapping_add(1);
-
                                 let tq = &mut *#tq.get_mut_unchecked().as_mut_ptr();
 
-                                tq.enqueue_unchecked(
+                                let key = tq.enqueue_unchecked(
                                     nr,
                                     || #enable_interrupt,
                                     || #pend,
                                     #m_ident.get_mut_unchecked().as_mut());
+                                #record_tq
 
-                                Ok(#name::#m::SpawnHandle { marker })
+                                Ok(#name::#m::SpawnHandle { key, index })
                             })
                         } else {
-                            Err(input)
+                            Err(rtic::SpawnError::Full(input))
                         }
                     }
                 }
             ));
         }
+
+        // Periodic tasks: `start` / `stop`
+        if extra.tasks[name].period.is_some() {
+            let monotonic = util::default_monotonic(app).expect("RTIC-ICE: default monotonic");
+            let m = &monotonic.ident;
+            let monotonic_name = m.to_string();
+            let tq = util::tq_ident(&monotonic_name);
+            let tq_internal = util::mark_internal_ident(&tq);
+            let record_tq = util::stats_record(
+                &tq,
+                quote!((&*#tq_internal.get_unchecked().as_ptr()).0.len()),
+            );
+            let tq = tq_internal;
+            let m_ident = util::monotonic_ident(&monotonic_name);
+            let m_ident = util::mark_internal_ident(&m_ident);
+            let instants = util::monotonic_instants_ident(name, m);
+            let instants = util::mark_internal_ident(&instants);
+            let periodic = util::periodic_ident(name);
+            let periodic = util::mark_internal_ident(&periodic);
+            let t = util::schedule_t_ident();
+            let (enable_interrupt, pend) = util::monotonic_enable_and_pend(monotonic);
+            let offset = extra.tasks[name].offset.iter();
+
+            let internal_start_ident = util::internal_task_ident(name, "start");
+            let internal_stop_ident = util::internal_task_ident(name, "stop");
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Starts releasing the task periodically
+                ///
+                /// The first release happens after `offset` (if given) from now. Returns `Err(())`
+                /// if the task is already running or if the instance released before the last
+                /// `stop` has not been dispatched yet.
+                ///
+                /// When called in `#[init]` the offset is relative to the moment `#[init]` returns
+                pub fn #internal_start_ident() -> Result<(), ()> {
+                    rtic::export::interrupt::free(|_| unsafe {
+                        if #periodic.get_unchecked().is_some() {
+                            return Err(());
+                        }
+
+                        if let Some(index) = #fq_dequeue {
+                            // Moved onto the time base of the monotonic once `#[init]` returns
+                            let instant = if #m_ident.get_unchecked().is_none() {
+                                rtic::time::Instant::new(0)
+                            } else {
+                                monotonics::#m::now()
+                            } #(+ #offset)*;
+
+                            #instants
+                                .get_mut_unchecked()
+                                .get_unchecked_mut(usize::from(index))
+                                .as_mut_ptr()
+                                .write(instant);
+
+                            let nr = rtic::export::NotReady {
+                                instant,
+                                index,
+                                task: #t::#name,
+                            };
+
+                            let key = (&mut *#tq.get_mut_unchecked().as_mut_ptr()).enqueue_unchecked(
+                                nr,
+                                || #enable_interrupt,
+                                || #pend,
+                                #m_ident.get_mut_unchecked().as_mut());
+                            #record_tq
+
+                            *#periodic.get_mut_unchecked() = Some((index, key));
+
+                            Ok(())
+                        } else {
+                            Err(())
+                        }
+                    })
+                }
+
+                #(#cfgs)*
+                /// Stops releasing the task
+                ///
+                /// An instance that has already been released is still dispatched.
+                pub fn #internal_stop_ident() {
+                    rtic::export::interrupt::free(|_| unsafe {
+                        if let Some((_, key)) = #periodic.get_mut_unchecked().take() {
+                            let tq = &mut *#tq.get_mut_unchecked().as_mut_ptr();
+
+                            if let Some((_task, index)) = tq.cancel(key) {
+                                // Return the index to the free queue
+                                #fq.get_mut_unchecked().split().0.enqueue_unchecked(index);
+                            }
+                        }
+                    })
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_start_ident as start;
+                #(#cfgs)*
+                pub use super::#internal_stop_ident as stop;
+            ));
+        }
+
+        // Lateness statistics: `lateness`
+        if extra.tasks[name].max_latency.is_some() {
+            let lateness = util::lateness_ident(name);
+            let lateness = util::mark_internal_ident(&lateness);
+            let internal_lateness_ident = util::internal_task_ident(name, "lateness");
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Lateness statistics of the releases of this task from the timer queue
+                pub fn #internal_lateness_ident() -> rtic::Lateness {
+                    rtic::export::interrupt::free(|_| unsafe { *#lateness.get_unchecked() })
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_lateness_ident as lateness;
+            ));
+        }
+    }
+
+    // Execution time statistics: `execution_time`
+    if let Context::HardwareTask(..) | Context::SoftwareTask(..) = ctxt {
+        if extra.tasks[name].has_budget() {
+            let stats = util::execution_time_ident(name);
+            let stats = util::mark_internal_ident(&stats);
+            let internal_execution_time_ident = util::internal_task_ident(name, "execution_time");
+
+            items.push(quote!(
+                #(#cfgs)*
+                /// Execution time statistics of this task, measured against its budget
+                pub fn #internal_execution_time_ident() -> rtic::ExecutionTime {
+                    rtic::export::interrupt::free(|_| unsafe { *#stats.get_unchecked() })
+                }
+            ));
+
+            module_items.push(quote!(
+                #(#cfgs)*
+                pub use super::#internal_execution_time_ident as execution_time;
+            ));
+        }
     }
 
     if !items.is_empty() {
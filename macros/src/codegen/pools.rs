@@ -0,0 +1,73 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::{check::Extra, codegen::util};
+
+/// Generates the `#[pool]`s and the `pools` module, through which their blocks are allocated
+pub fn codegen(extra: &Extra) -> (Vec<TokenStream2>, TokenStream2) {
+    let mut mod_app = vec![];
+
+    if extra.pools.is_empty() {
+        return (mod_app, quote!());
+    }
+
+    let pooled = util::mark_internal_name("Pooled");
+    mod_app.push(quote!(
+        #[doc(hidden)]
+        pub trait #pooled: Sized + 'static {
+            fn pool() -> &'static rtic::export::Pool<Self>;
+        }
+    ));
+
+    for (i, pool) in extra.pools.iter().enumerate() {
+        let ty = &pool.ty;
+        let n = pool.capacity;
+        let ident = util::pool_ident(i);
+        let ident = util::mark_internal_ident(&ident);
+        let memory = util::pool_memory_ident(i);
+        let memory = util::mark_internal_ident(&memory);
+
+        mod_app.push(quote!(
+            #[doc(hidden)]
+            static #ident: rtic::export::Pool<#ty> = rtic::export::Pool::new();
+
+            // Handed over to the pool in `pre_init`
+            #[doc(hidden)]
+            static #memory: rtic::RacyCell<
+                core::mem::MaybeUninit<[rtic::export::PoolNode<#ty>; #n]>,
+            > = rtic::RacyCell::new(core::mem::MaybeUninit::uninit());
+
+            impl #pooled for #ty {
+                #[inline(always)]
+                fn pool() -> &'static rtic::export::Pool<Self> {
+                    &#ident
+                }
+            }
+        ));
+    }
+
+    let mod_pools = quote!(
+        /// Memory pools declared with `#[pool(..)]`, looked up by the type of their values
+        pub mod pools {
+            pub use super::#pooled as Pooled;
+
+            /// Moves `value` into a free block of its pool, or hands it back if there's none
+            pub fn alloc<T>(value: T) -> Result<rtic::Box<T>, T>
+            where
+                T: Pooled,
+            {
+                T::pool().alloc(value)
+            }
+
+            /// The number of free blocks in the pool of `T`
+            pub fn available<T>() -> usize
+            where
+                T: Pooled,
+            {
+                T::pool().available()
+            }
+        }
+    );
+
+    (mod_app, mod_pools)
+}
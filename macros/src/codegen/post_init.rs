@@ -3,10 +3,10 @@ use quote::quote;
 use rtic_syntax::ast::App;
 use syn::Index;
 
-use crate::{analyze::Analysis, codegen::util};
+use crate::{analyze::Analysis, check::Extra, codegen::util};
 
 /// Generates code that runs after `#[init]` returns
-pub fn codegen(app: &App, analysis: &Analysis) -> Vec<TokenStream2> {
+pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream2> {
     let mut stmts = vec![];
 
     // Initialize late resources
@@ -45,6 +45,65 @@ pub fn codegen(app: &App, analysis: &Analysis) -> Vec<TokenStream2> {
         let name = util::monotonic_ident(&monotonic.to_string());
         let name = util::mark_internal_ident(&name);
         stmts.push(quote!(*#name.get_mut_unchecked() = Some(monotonics.#idx);));
+
+        // Tasks scheduled during `init` used a time base that starts at `Instant::new(0)`; move
+        // them onto the time base of the monotonic, which need not have been reset to zero
+        let tq = util::tq_ident(&monotonic.to_string());
+        let tq = util::mark_internal_ident(&tq);
+        let t = util::schedule_t_ident();
+        let mut arms = app
+            .software_tasks
+            .iter()
+            .map(|(name, task)| {
+                let cfgs = &task.cfgs;
+                let instants = util::monotonic_instants_ident(name, monotonic);
+                let instants = util::mark_internal_ident(&instants);
+
+                quote!(
+                    #(#cfgs)*
+                    #t::#name => #instants
+                        .get_mut_unchecked()
+                        .get_unchecked_mut(usize::from(nr.index))
+                        .as_mut_ptr()
+                        .write(nr.instant),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if util::has_async_tasks(app, extra) {
+            // Delays keep their instant in the timer queue only
+            let delay = util::delay_variant_ident();
+            arms.push(quote!(#t::#delay(_) => {}));
+        }
+
+        stmts.push(quote!(
+            let epoch = monotonics::#monotonic::now().duration_since_epoch().integer();
+            (&mut *#tq.get_mut_unchecked().as_mut_ptr()).rebase(|nr| {
+                nr.instant = rtic::time::Instant::new(
+                    nr.instant.duration_since_epoch().integer().wrapping_add(epoch),
+                );
+
+                match nr.task {
+                    #(#arms)*
+                }
+            });
+        ));
+
+        // So did the deadlines of the instances spawned on EDF levels
+        if util::default_monotonic(app).map_or(false, |default| default.ident == *monotonic) {
+            for level in &analysis.edf {
+                let rq = util::rq_ident(*level);
+                let rq = util::mark_internal_ident(&rq);
+
+                stmts.push(quote!(
+                    (&mut *#rq.get_mut_unchecked().as_mut_ptr()).rebase(|deadline| {
+                        *deadline = rtic::time::Instant::new(
+                            deadline.duration_since_epoch().integer().wrapping_add(epoch),
+                        );
+                    });
+                ));
+            }
+        }
     }
 
     // Enable the interrupts -- this completes the `init`-ialization phase
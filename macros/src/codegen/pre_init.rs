@@ -24,6 +24,48 @@ pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream
         ));
     }
 
+    // Initialize the ready queues
+    for level in analysis.channels.keys() {
+        let rq = util::rq_ident(*level);
+        let rq = util::mark_internal_ident(&rq);
+        let queue = if analysis.edf.contains(level) {
+            quote!(rtic::export::EdfQueue)
+        } else {
+            quote!(rtic::export::ReadyQueue)
+        };
+
+        stmts.push(quote!(#rq.get_mut_unchecked().as_mut_ptr().write(#queue::new());));
+    }
+
+    if cfg!(feature = "stats") {
+        let stats = util::mark_internal_name("STATS");
+
+        stmts.push(quote!(rtic::export::register_stats(#stats);));
+    }
+
+    if cfg!(feature = "lock-trace") {
+        let locks = util::mark_internal_name("LOCK_STATS");
+
+        stmts.push(quote!(rtic::export::register_locks(#locks);));
+    }
+
+    // Hand the memory of the pools over to them
+    for (i, pool) in extra.pools.iter().enumerate() {
+        let ty = &pool.ty;
+        let n = pool.capacity;
+        let ident = util::pool_ident(i);
+        let ident = util::mark_internal_ident(&ident);
+        let memory = util::pool_memory_ident(i);
+        let memory = util::mark_internal_ident(&memory);
+
+        stmts.push(quote!(
+            #ident.manage(
+                #memory.get_mut_unchecked().as_mut_ptr() as *mut rtic::export::PoolNode<#ty>,
+                #n,
+            );
+        ));
+    }
+
     stmts.push(quote!(
         // To set the variable in cortex_m so the peripherals cannot be taken multiple times
         let mut core: rtic::export::Peripherals = rtic::export::Peripherals::steal().into();
@@ -32,7 +74,54 @@ pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream
     let device = &extra.device;
     let nvic_prio_bits = quote!(#device::NVIC_PRIO_BITS);
 
-    let interrupt_ids = analysis.interrupts.iter().map(|(p, (id, _))| (p, id));
+    // The multiplexed dispatcher serves several levels; it's set up once, at the highest of them
+    let highest_multiplexed = analysis.multiplexed.iter().next_back();
+    let dispatchers = analysis
+        .interrupts
+        .iter()
+        .filter(|(level, _)| {
+            !analysis.multiplexed.contains(level) || Some(*level) == highest_multiplexed
+        })
+        .collect::<Vec<_>>();
+
+    // The host backend has no vector table; hook the handlers up to the emulated NVIC instead
+    if cfg!(feature = "host") {
+        let no_cfgs = vec![];
+        let handlers = dispatchers
+            .iter()
+            .map(|(_, (name, _))| (name, &no_cfgs))
+            .chain(
+                app.hardware_tasks
+                    .values()
+                    .map(|task| (&task.args.binds, &task.cfgs)),
+            )
+            .chain(
+                app.monotonics
+                    .values()
+                    .map(|monotonic| (&monotonic.args.binds, &no_cfgs)),
+            );
+
+        for (name, cfgs) in handlers {
+            if util::is_exception(name) {
+                stmts.push(quote!(
+                    #(#cfgs)*
+                    rtic::export::register_exception(rtic::export::SystemHandler::#name, #name);
+                ));
+            } else {
+                let interrupt = util::interrupt_ident();
+                stmts.push(quote!(
+                    #(#cfgs)*
+                    rtic::export::register_interrupt(#rt_err::#interrupt::#name, #name);
+                ));
+            }
+        }
+    }
+
+    // Exception dispatchers are done in the exception pass
+    let interrupt_ids = dispatchers
+        .iter()
+        .map(|&(p, (id, _))| (p, id))
+        .filter(|(_, id)| !util::is_exception(id));
 
     // Unmask interrupts and set their priorities
     for (&priority, name) in interrupt_ids.chain(app.hardware_tasks.values().flat_map(|task| {
@@ -60,14 +149,26 @@ pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream
         stmts.push(quote!(rtic::export::NVIC::unmask(#rt_err::#interrupt::#name);));
     }
 
-    // Set exception priorities
-    for (name, priority) in app.hardware_tasks.values().filter_map(|task| {
-        if util::is_exception(&task.args.binds) {
-            Some((&task.args.binds, task.args.priority))
+    // Set exception priorities, of hardware tasks and of dispatchers
+    let exception_dispatchers = dispatchers.iter().filter_map(|&(&p, (id, _))| {
+        if util::is_exception(id) {
+            Some((id, p))
         } else {
             None
         }
-    }) {
+    });
+    for (name, priority) in app
+        .hardware_tasks
+        .values()
+        .filter_map(|task| {
+            if util::is_exception(&task.args.binds) {
+                Some((&task.args.binds, task.args.priority))
+            } else {
+                None
+            }
+        })
+        .chain(exception_dispatchers)
+    {
         // Compile time assert that this priority is supported by the device
         stmts.push(quote!(let _ = [(); ((1 << #nvic_prio_bits) - #priority as usize)];));
 
@@ -104,7 +205,7 @@ pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream
 
                 // Always enable monotonic interrupts if they should never be off
                 if !<#mono_type as rtic::Monotonic>::DISABLE_INTERRUPT_ON_EMPTY_QUEUE {
-                    core::mem::transmute::<_, cortex_m::peripheral::SYST>(())
+                    core::mem::transmute::<_, rtic::export::SYST>(())
                         .enable_interrupt();
                 }
             ));
@@ -125,6 +226,18 @@ pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream
         }
     }
 
+    // Start the periodic tasks before `#[init]`, which may `stop` them; their first release is
+    // moved onto the time base of the monotonic when `#[init]` returns
+    for (name, task) in &app.software_tasks {
+        if extra.tasks[name].period.is_some() {
+            let cfgs = &task.cfgs;
+            stmts.push(quote!(
+                #(#cfgs)*
+                #name::start().ok();
+            ));
+        }
+    }
+
     // If there's no user `#[idle]` then optimize returning from interrupt handlers
     if app.idles.is_empty() {
         // Set SLEEPONEXIT bit to enter sleep mode when returning from ISR
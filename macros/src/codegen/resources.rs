@@ -24,8 +24,12 @@ pub fn codegen(
 ) {
     let mut mod_app = vec![];
     let mut mod_resources = vec![];
+    let mut names = vec![];
+    let mut lock_stats = vec![];
+
+    for (id, (name, res, expr, _)) in app.resources(analysis).enumerate() {
+        names.push(name.to_string());
 
-    for (name, res, expr, _) in app.resources(analysis) {
         let cfgs = &res.cfgs;
         let ty = &res.ty;
         let mangled_name = util::mark_internal_ident(&name);
@@ -126,11 +130,28 @@ pub fn codegen(
             // For future use
             // let doc = format!(" RTIC internal ({} resource): {}:{}", doc, file!(), line!());
 
+            if cfg!(feature = "lock-trace") {
+                let name_s = name.to_string();
+                let stats = util::lock_stats_ident(name);
+                let stats = util::mark_internal_ident(&stats);
+
+                mod_app.push(quote!(
+                    #(#cfgs)*
+                    #[allow(non_upper_case_globals)]
+                    #[doc(hidden)]
+                    static #stats: rtic::export::LockCounter =
+                        rtic::export::LockCounter::new(#name_s, #ceiling);
+                ));
+
+                lock_stats.push(quote!(#(#cfgs)* &#stats));
+            }
+
             mod_app.push(util::impl_mutex(
                 extra,
                 cfgs,
                 true,
                 name,
+                id as u16,
                 quote!(#ty),
                 ceiling,
                 ptr,
@@ -138,6 +159,19 @@ pub fn codegen(
         }
     }
 
+    if cfg!(feature = "lock-trace") {
+        let locks = util::mark_internal_name("LOCK_STATS");
+        let n = names.len();
+
+        mod_app.push(quote!(
+            #[doc(hidden)]
+            static #locks: &[&rtic::export::LockCounter] = &[#(#lock_stats,)*];
+
+            /// Names of the resources, indexed by the IDs passed to `rtic::Tracer::lock`
+            pub const RESOURCE_NAMES: [&str; #n] = [#(#names,)*];
+        ));
+    }
+
     let mod_resources = if mod_resources.is_empty() {
         quote!()
     } else {
@@ -31,7 +31,7 @@ pub fn codegen(
         let inputs = &task.inputs;
         let (_, _, _, input_ty) = util::regroup_inputs(inputs);
 
-        let cap = task.args.capacity;
+        let cap = usize::from(task.args.capacity);
         let cap_lit = util::capacity_literal(cap);
         let cap_ty = util::capacity_typenum(cap, true);
 
@@ -76,6 +76,80 @@ pub fn codegen(
                     rtic::RacyCell<[core::mem::MaybeUninit<rtic::time::Instant<#mono_type>>; #cap_lit]> =
                     rtic::RacyCell::new([#(#elems,)*]);
             ));
+
+            let outstanding = util::monotonic_outstanding_ident(name, &monotonic.ident);
+            let outstanding = util::mark_internal_ident(&outstanding);
+            mod_app.push(quote!(
+                // /// Timer queue key of the instance `spawn_or_reschedule_after` controls
+                #[doc(hidden)]
+                static #outstanding: rtic::RacyCell<Option<rtic::export::Key>> =
+                    rtic::RacyCell::new(None);
+            ));
+
+            let cancelled = util::monotonic_cancelled_ident(name, &monotonic.ident);
+            let cancelled = util::mark_internal_ident(&cancelled);
+            let nones = (0..cap).map(|_| quote!(None));
+            mod_app.push(quote!(
+                // /// Timer queue key of the last instance cancelled through a handle, per slot,
+                // /// so that the other handles of that instance can tell they are stale
+                #[doc(hidden)]
+                static #cancelled: rtic::RacyCell<[Option<rtic::export::Key>; #cap_lit]> =
+                    rtic::RacyCell::new([#(#nones,)*]);
+            ));
+
+            let released = util::monotonic_released_ident(name, &monotonic.ident);
+            let released = util::mark_internal_ident(&released);
+            let nones = (0..cap).map(|_| quote!(None));
+            mod_app.push(quote!(
+                // /// Timer queue key and ready queue key of the last instance released from the
+                // /// timer queue, per slot, so that `SpawnHandle::cancel` can follow it
+                #[doc(hidden)]
+                static #released:
+                    rtic::RacyCell<[Option<(rtic::export::Key, rtic::export::ReadyKey)>; #cap_lit]> =
+                    rtic::RacyCell::new([#(#nones,)*]);
+            ));
+        }
+
+        if extra.tasks[name].period.is_some() {
+            let periodic = util::periodic_ident(name);
+            let periodic = util::mark_internal_ident(&periodic);
+            mod_app.push(quote!(
+                // /// Free queue slot and timer queue key of the next release of a periodic task
+                #[doc(hidden)]
+                static #periodic: rtic::RacyCell<Option<(u8, rtic::export::Key)>> =
+                    rtic::RacyCell::new(None);
+            ));
+        }
+
+        if inputs.is_empty() {
+            let unique = util::unique_ident(name);
+            let unique = util::mark_internal_ident(&unique);
+            mod_app.push(quote!(
+                // /// Ready queue key of the instance spawned by `spawn_unique`
+                #[doc(hidden)]
+                static #unique: rtic::RacyCell<Option<rtic::export::ReadyKey>> =
+                    rtic::RacyCell::new(None);
+            ));
+        }
+
+        if extra.tasks[name].max_latency.is_some() {
+            let lateness = util::lateness_ident(name);
+            let lateness = util::mark_internal_ident(&lateness);
+            mod_app.push(quote!(
+                #[doc(hidden)]
+                static #lateness: rtic::RacyCell<rtic::Lateness> =
+                    rtic::RacyCell::new(rtic::Lateness::new());
+            ));
+        }
+
+        if extra.tasks[name].has_budget() {
+            let stats = util::execution_time_ident(name);
+            let stats = util::mark_internal_ident(&stats);
+            mod_app.push(quote!(
+                #[doc(hidden)]
+                static #stats: rtic::RacyCell<rtic::ExecutionTime> =
+                    rtic::RacyCell::new(rtic::ExecutionTime::new());
+            ));
         }
 
         let uninit = mk_uninit();
@@ -100,6 +174,30 @@ pub fn codegen(
             mod_app.push(constructor);
         }
 
+        if extra.tasks[name].is_async {
+            // Each instance keeps its future, and with it its slot, until the future completes
+            let futures = util::futures_ident(name);
+            let futures = util::mark_internal_ident(&futures);
+            let (_, tupled, pats, _) = util::regroup_inputs(inputs);
+            let args = &extra.tasks[name];
+            let context_lt = if needs_lt || !args.senders.is_empty() || !args.receivers.is_empty() {
+                Some(quote!(<'static>))
+            } else {
+                None
+            };
+            let size = quote!(rtic::export::size_of_future(
+                &|cx: #name::Context #context_lt, #tupled: #input_ty| #name(cx #(,#pats)*)
+            ));
+
+            let pend = util::pend_dispatcher(analysis, extra, task.args.priority);
+            let slots = (0..cap).map(|_| quote!(rtic::export::FutureSlot::new(|| #pend)));
+            mod_app.push(quote!(
+                #[doc(hidden)]
+                static #futures: rtic::RacyCell<[rtic::export::FutureSlot<{ #size }>; #cap_lit]> =
+                    rtic::RacyCell::new([#(#slots,)*]);
+            ));
+        }
+
         // `${task}Locals`
         let mut locals_pat = None;
         if !task.locals.is_empty() {
@@ -115,11 +213,21 @@ pub fn codegen(
             let cfgs = &task.cfgs;
             let stmts = &task.stmts;
             let locals_pat = locals_pat.iter();
+            let async_ = if extra.tasks[name].is_async {
+                Some(quote!(async))
+            } else {
+                None
+            };
+            // `by_ref` tasks get the references they were written with back
+            let mut inputs = inputs.clone();
+            for (input, ty) in inputs.iter_mut().zip(&extra.tasks[name].ref_types) {
+                input.ty = Box::new(ty.clone());
+            }
             user_tasks.push(quote!(
                 #(#attrs)*
                 #(#cfgs)*
                 #[allow(non_snake_case)]
-                fn #name(#(#locals_pat,)* #context: #name::Context #(,#inputs)*) {
+                #async_ fn #name(#(#locals_pat,)* #context: #name::Context #(,#inputs)*) {
                     use rtic::Mutex as _;
                     use rtic::mutex_prelude::*;
 
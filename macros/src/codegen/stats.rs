@@ -0,0 +1,70 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use rtic_syntax::ast::App;
+use syn::{Attribute, Ident};
+
+use crate::{analyze::Analysis, check::Extra, codegen::util};
+
+/// Generates the counters of the queue statistics (`stats` feature)
+pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream2> {
+    let mut mod_app = vec![];
+
+    if !cfg!(feature = "stats") {
+        return mod_app;
+    }
+
+    let mut counters = vec![];
+    let mut push = |cfgs: &[Attribute], queue: Ident, id: TokenStream2, capacity: usize| {
+        let stats = util::stats_ident(&queue);
+        let stats = util::mark_internal_ident(&stats);
+        let capacity = util::capacity_literal(capacity);
+
+        mod_app.push(quote!(
+            #(#cfgs)*
+            #[doc(hidden)]
+            static #stats: rtic::export::StatsCounter =
+                rtic::export::StatsCounter::new(#id, #capacity);
+        ));
+
+        counters.push(quote!(#(#cfgs)* &#stats));
+    };
+
+    for (name, task) in &app.software_tasks {
+        let name_s = name.to_string();
+
+        push(
+            &task.cfgs,
+            util::fq_ident(name),
+            quote!(rtic::QueueId::Free(#name_s)),
+            task.args.capacity.into(),
+        );
+    }
+
+    for (&level, channel) in &analysis.channels {
+        push(
+            &[],
+            util::rq_ident(level),
+            quote!(rtic::QueueId::Ready(#level)),
+            channel.capacity.into(),
+        );
+    }
+
+    for (_, monotonic) in &app.monotonics {
+        let name_s = monotonic.ident.to_string();
+
+        push(
+            &[],
+            util::tq_ident(&name_s),
+            quote!(rtic::QueueId::Timer(#name_s)),
+            util::tq_capacity(app, extra),
+        );
+    }
+
+    let all = util::mark_internal_name("STATS");
+    mod_app.push(quote!(
+        #[doc(hidden)]
+        static #all: &[&rtic::export::StatsCounter] = &[#(#counters,)*];
+    ));
+
+    mod_app
+}
@@ -5,24 +5,18 @@ use rtic_syntax::ast::App;
 use crate::{analyze::Analysis, check::Extra, codegen::util};
 
 /// Generates timer queues and timer queue handlers
-pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStream2> {
+pub fn codegen(app: &App, analysis: &Analysis, extra: &Extra) -> Vec<TokenStream2> {
     let mut items = vec![];
 
-    if !app.monotonics.is_empty() {
-        // Generate the marker counter used to track for `cancel` and `reschedule`
-        let tq_marker = util::mark_internal_ident(&util::timer_queue_marker_ident());
-        items.push(quote!(
-            // #[doc = #doc]
-            #[doc(hidden)]
-            #[allow(non_camel_case_types)]
-            static #tq_marker: rtic::RacyCell<u32> = rtic::RacyCell::new(0);
-        ));
+    let has_async_tasks = util::has_async_tasks(app, extra);
+    let delay = util::delay_variant_ident();
 
+    if !app.monotonics.is_empty() {
         let t = util::schedule_t_ident();
 
         // Enumeration of `schedule`-able tasks
         {
-            let variants = app
+            let mut variants = app
                 .software_tasks
                 .iter()
                 .map(|(name, task)| {
@@ -35,6 +29,11 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
                 })
                 .collect::<Vec<_>>();
 
+            if has_async_tasks {
+                // The waker of a pending `Delay`
+                variants.push(quote!(#delay(*mut Option<core::task::Waker>)));
+            }
+
             // For future use
             // let doc = "Tasks that can be scheduled".to_string();
             items.push(quote!(
@@ -62,12 +61,7 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
         {
             // For future use
             // let doc = &format!("Timer queue for {}", monotonic_name);
-            let cap = app
-                .software_tasks
-                .iter()
-                .map(|(_name, task)| task.args.capacity)
-                .sum();
-            let n = util::capacity_typenum(cap, false);
+            let n = util::capacity_typenum(util::tq_capacity(app, extra), false);
             let tq_ty =
                 quote!(core::mem::MaybeUninit<rtic::export::TimerQueue<#mono_type, #t, #n>>);
 
@@ -101,44 +95,120 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
                 .map(|(name, task)| {
                     let cfgs = &task.cfgs;
                     let priority = task.args.priority;
-                    let rq = util::rq_ident(priority);
-                    let rq = util::mark_internal_ident(&rq);
-                    let rqt = util::spawn_t_ident(priority);
 
-                    // The interrupt that runs the task dispatcher
-                    let interrupt = &analysis.interrupts.get(&priority).expect("RTIC-ICE: interrupt not found").0;
+                    // Pends the interrupt that runs the task dispatcher
+                    let pend = util::pend_dispatcher(analysis, extra, priority);
+
+                    // Lateness is tracked on the default monotonic only
+                    let lateness = match &extra.tasks[name].max_latency {
+                        Some(max_latency) if monotonic.args.default => {
+                            let m = &monotonic.ident;
+                            let instants = util::monotonic_instants_ident(name, m);
+                            let instants = util::mark_internal_ident(&instants);
+                            let stats = util::lateness_ident(name);
+                            let stats = util::mark_internal_ident(&stats);
+                            let name_s = name.to_string();
+                            let on_miss = extra.deadline_miss.iter();
+
+                            quote!(
+                                let now = monotonics::#m::now();
+                                let instant = #instants
+                                    .get_unchecked()
+                                    .get_unchecked(usize::from(index))
+                                    .as_ptr()
+                                    .read();
+                                let lateness: u64 = now
+                                    .checked_duration_since(&instant)
+                                    .map(|lateness| lateness.integer().into())
+                                    .unwrap_or(0);
+                                let max_latency: u64 = (rtic::time::Instant::<#mono_type>::new(0) + #max_latency)
+                                    .duration_since_epoch()
+                                    .integer()
+                                    .into();
+
+                                if rtic::export::interrupt::free(|_| #stats.get_mut_unchecked().record(lateness, max_latency)) {
+                                    #(#on_miss(#name_s, lateness);)*
+                                }
+                            )
+                        }
+                        _ => quote!(),
+                    };
+
+                    // EDF dispatchers order the releases by the instant they were scheduled at
+                    let deadline = if analysis.edf.contains(&priority) {
+                        let deadline = if monotonic.args.default {
+                            let instants = util::monotonic_instants_ident(name, &monotonic.ident);
+                            let instants = util::mark_internal_ident(&instants);
+
+                            quote!(#instants.get_unchecked().get_unchecked(usize::from(index)).as_ptr().read())
+                        } else {
+                            let m = &util::default_monotonic(app).expect("RTIC-ICE: default monotonic").ident;
+
+                            quote!(monotonics::#m::now())
+                        };
 
-                    let pend = {
-                        quote!(
-                            rtic::pend(#rt_err::#enum_::#interrupt);
-                        )
+                        quote!(let deadline = #deadline;)
+                    } else {
+                        quote!()
                     };
+                    let enqueue = util::rq_enqueue(app, analysis, extra, priority, name, quote!(deadline));
+                    let released = util::monotonic_released_ident(name, &monotonic.ident);
+                    let released = util::mark_internal_ident(&released);
 
                     quote!(
                         #(#cfgs)*
                         #t::#name => {
-                            rtic::export::interrupt::free(|_| #rq.get_mut_unchecked().split().0.enqueue_unchecked((#rqt::#name, index)));
+                            #lateness
+                            #deadline
 
-                            #pend
+                            rtic::export::interrupt::free(|_| {
+                                let ready = #enqueue;
+
+                                // Lets the `SpawnHandle` cancel the instance until it's dispatched
+                                *#released.get_mut_unchecked().get_unchecked_mut(usize::from(index)) =
+                                    Some((key, ready));
+                            });
+
+                            #pend;
                         }
                     )
                 })
                 .collect::<Vec<_>>();
 
+            // Delays are woken in the critical section that dequeues them, so they can't be
+            // dropped, taking their waker with them, before that
+            let (wake, delay_arm) = if has_async_tasks {
+                (
+                    quote!(.map(|(task, index, key)| {
+                        if let #t::#delay(waker) = task {
+                            if let Some(waker) = (*waker).take() {
+                                waker.wake();
+                            }
+                        }
+
+                        (task, index, key)
+                    })),
+                    quote!(#t::#delay(_) => {}),
+                )
+            } else {
+                (quote!(), quote!())
+            };
+
             let bound_interrupt = &monotonic.args.binds;
             let disable_isr = if &*bound_interrupt.to_string() == "SysTick" {
-                quote!(core::mem::transmute::<_, cortex_m::peripheral::SYST>(()).disable_interrupt())
+                quote!(core::mem::transmute::<_, rtic::export::SYST>(()).disable_interrupt())
             } else {
                 quote!(rtic::export::NVIC::mask(#rt_err::#enum_::#bound_interrupt))
             };
 
-            items.push(quote!(
-                #[no_mangle]
-                #[allow(non_snake_case)]
-                unsafe fn #bound_interrupt() {
-                    while let Some((task, index)) = rtic::export::interrupt::free(|_|
+            let body = util::trace(
+                app,
+                extra,
+                &monotonic.ident,
+                quote!(
+                    while let Some((task, index, key)) = rtic::export::interrupt::free(|_|
                         if let Some(mono) = #m_ident.get_mut_unchecked().as_mut() {
-                            (&mut *#tq.get_mut_unchecked().as_mut_ptr()).dequeue(|| #disable_isr, mono)
+                            (&mut *#tq.get_mut_unchecked().as_mut_ptr()).dequeue(|| #disable_isr, mono)#wake
                         } else {
                             // We can only use the timer queue if `init` has returned, and it
                             // writes the `Some(monotonic)` we are accessing here.
@@ -147,12 +217,21 @@ pub fn codegen(app: &App, analysis: &Analysis, _extra: &Extra) -> Vec<TokenStrea
                     {
                         match task {
                             #(#arms)*
+                            #delay_arm
                         }
                     }
 
                     rtic::export::interrupt::free(|_| if let Some(mono) = #m_ident.get_mut_unchecked().as_mut() {
                         mono.on_interrupt();
                     });
+                ),
+            );
+
+            items.push(quote!(
+                #[no_mangle]
+                #[allow(non_snake_case)]
+                unsafe fn #bound_interrupt() {
+                    #body
                 }
             ));
         }
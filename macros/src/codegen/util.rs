@@ -1,19 +1,22 @@
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
-use rtic_syntax::{ast::App, Context};
-use syn::{Attribute, Ident, LitInt, PatType};
+use quote::{quote, ToTokens};
+use rtic_syntax::{
+    ast::{App, Monotonic},
+    Context,
+};
+use syn::{Attribute, GenericArgument, Ident, LitInt, PatType, PathArguments, Type};
 
-use crate::check::Extra;
+use crate::{analyze::Analysis, check::Extra};
 
 /// Turns `capacity` into an unsuffixed integer literal
-pub fn capacity_literal(capacity: u8) -> LitInt {
+pub fn capacity_literal(capacity: usize) -> LitInt {
     LitInt::new(&capacity.to_string(), Span::call_site())
 }
 
 /// Turns `capacity` into a type-level (`typenum`) integer
-pub fn capacity_typenum(capacity: u8, round_up_to_power_of_two: bool) -> TokenStream2 {
+pub fn capacity_typenum(capacity: usize, round_up_to_power_of_two: bool) -> TokenStream2 {
     let capacity = if round_up_to_power_of_two {
         capacity.checked_next_power_of_two().expect("UNREACHABLE")
     } else {
@@ -30,12 +33,76 @@ pub fn fq_ident(task: &Ident) -> Ident {
     Ident::new(&format!("{}_FQ", task.to_string()), Span::call_site())
 }
 
+/// Generates the code that takes a free slot of `task`, if there's one
+///
+/// With the `stats` feature this also records how many slots are in use, or that there was none
+/// left. The caller must be in a critical section.
+pub fn fq_dequeue(task: &Ident, capacity: u8) -> TokenStream2 {
+    let fq = mark_internal_ident(&fq_ident(task));
+
+    if cfg!(feature = "stats") {
+        let capacity = capacity_literal(capacity.into());
+        let stats = mark_internal_ident(&stats_ident(&fq_ident(task)));
+
+        quote!({
+            let index = #fq.get_mut_unchecked().dequeue();
+            if index.is_some() {
+                #stats.record(#capacity - usize::from(#fq.get_unchecked().len()));
+            } else {
+                #stats.fail();
+            }
+
+            index
+        })
+    } else {
+        quote!(#fq.get_mut_unchecked().dequeue())
+    }
+}
+
+/// Generates an identifier for the statistics of `queue` (`stats` feature)
+pub fn stats_ident(queue: &Ident) -> Ident {
+    Ident::new(&format!("{}_STATS", queue), Span::call_site())
+}
+
+/// Generates the code that records that `len` entries of `queue` are in use, with the `stats`
+/// feature
+pub fn stats_record(queue: &Ident, len: TokenStream2) -> TokenStream2 {
+    if cfg!(feature = "stats") {
+        let stats = mark_internal_ident(&stats_ident(queue));
+
+        quote!(#stats.record(#len);)
+    } else {
+        quote!()
+    }
+}
+
+/// Generates the code that records that `queue` had no room left, with the `stats` feature
+pub fn stats_fail(queue: &Ident) -> TokenStream2 {
+    if cfg!(feature = "stats") {
+        let stats = mark_internal_ident(&stats_ident(queue));
+
+        quote!(#stats.fail();)
+    } else {
+        quote!()
+    }
+}
+
+/// Generates an identifier for the critical section times of the resource `name` (`lock-trace`
+/// feature)
+pub fn lock_stats_ident(name: &Ident) -> Ident {
+    Ident::new(&format!("{}_LOCK_STATS", name), Span::call_site())
+}
+
 /// Generates a `Mutex` implementation
+///
+/// With the `lock-trace` feature the critical sections are timed and reported under `id`.
+#[allow(clippy::too_many_arguments)]
 pub fn impl_mutex(
     extra: &Extra,
     cfgs: &[Attribute],
     resources_prefix: bool,
     name: &Ident,
+    id: u16,
     ty: TokenStream2,
     ceiling: u8,
     ptr: TokenStream2,
@@ -47,6 +114,45 @@ pub fn impl_mutex(
     };
 
     let device = &extra.device;
+    let lock = if cfg!(feature = "lock-trace") {
+        let stats = mark_internal_ident(&lock_stats_ident(name));
+        let hook = extra
+            .tracer
+            .as_ref()
+            .map(|tracer| quote!(<#tracer as rtic::Tracer>::lock(#id, CEILING, cycles);));
+
+        // Only the locks that raise the priority are timed
+        quote!(
+            let priority = #priority;
+
+            if priority.get() < CEILING {
+                let start = rtic::export::cycle_count();
+                let r = rtic::export::lock(
+                    #ptr,
+                    priority,
+                    CEILING,
+                    #device::NVIC_PRIO_BITS,
+                    f,
+                );
+                let cycles = rtic::export::cycle_count().wrapping_sub(start);
+                #stats.record(cycles);
+                #hook
+
+                r
+            } else {
+                rtic::export::lock(#ptr, priority, CEILING, #device::NVIC_PRIO_BITS, f)
+            }
+        )
+    } else {
+        quote!(rtic::export::lock(
+            #ptr,
+            #priority,
+            CEILING,
+            #device::NVIC_PRIO_BITS,
+            f,
+        ))
+    };
+
     quote!(
         #(#cfgs)*
         impl<'a> rtic::Mutex for #path<'a> {
@@ -58,19 +164,95 @@ pub fn impl_mutex(
                 const CEILING: u8 = #ceiling;
 
                 unsafe {
-                    rtic::export::lock(
-                        #ptr,
-                        #priority,
-                        CEILING,
-                        #device::NVIC_PRIO_BITS,
-                        f,
-                    )
+                    #lock
                 }
             }
         }
     )
 }
 
+/// The monotonic marked with `default = true`, if any
+pub fn default_monotonic(app: &App) -> Option<&Monotonic> {
+    app.monotonics
+        .values()
+        .find(|monotonic| monotonic.args.default)
+}
+
+/// Generates the code that enables and the code that pends the interrupt bound to a monotonic
+pub fn monotonic_enable_and_pend(monotonic: &Monotonic) -> (TokenStream2, TokenStream2) {
+    let m_isr = &monotonic.args.binds;
+    let enum_ = interrupt_ident();
+
+    if &*m_isr.to_string() == "SysTick" {
+        (
+            quote!(core::mem::transmute::<_, rtic::export::SYST>(()).enable_interrupt()),
+            quote!(rtic::export::SCB::set_pendst()),
+        )
+    } else {
+        let rt_err = rt_err_ident();
+        (
+            quote!(rtic::export::NVIC::unmask(#rt_err::#enum_::#m_isr)),
+            quote!(rtic::pend(#rt_err::#enum_::#m_isr)),
+        )
+    }
+}
+
+/// Generates the code that pends the dispatcher of priority `level`
+///
+/// Dispatchers are device interrupts, or the PendSV or SVCall exceptions.
+pub fn pend_dispatcher(analysis: &Analysis, extra: &Extra, level: u8) -> TokenStream2 {
+    if analysis.multiplexed.contains(&level) {
+        let pend = mark_internal_name("pend_multiplexed");
+
+        return quote!(#pend(#level));
+    }
+
+    pend_interrupt(analysis, extra, level)
+}
+
+/// Generates the code that pends the interrupt (or exception) of the dispatcher of `level`
+pub fn pend_interrupt(analysis: &Analysis, extra: &Extra, level: u8) -> TokenStream2 {
+    let interrupt = &analysis
+        .interrupts
+        .get(&level)
+        .expect("RTIC-ICE: interrupt identifer not found")
+        .0;
+
+    match &*interrupt.to_string() {
+        "PendSV" => quote!(rtic::export::SCB::set_pendsv()),
+        "SVCall" => quote!(rtic::export::pend_svcall!()),
+        _ => {
+            let device = &extra.device;
+            let enum_ = interrupt_ident();
+
+            quote!(rtic::pend(#device::#enum_::#interrupt))
+        }
+    }
+}
+
+/// Generates the code that sets the priority of the dispatcher `interrupt` to the logical `level`
+pub fn set_dispatcher_priority(
+    extra: &Extra,
+    interrupt: &Ident,
+    level: TokenStream2,
+) -> TokenStream2 {
+    let device = &extra.device;
+    let priority = quote!(rtic::export::logical2hw(#level, #device::NVIC_PRIO_BITS));
+
+    if is_exception(interrupt) {
+        quote!(rtic::export::Peripherals::steal()
+            .SCB
+            .set_priority(rtic::export::SystemHandler::#interrupt, #priority))
+    } else {
+        let rt_err = rt_err_ident();
+        let enum_ = interrupt_ident();
+
+        quote!(rtic::export::Peripherals::steal()
+            .NVIC
+            .set_priority(#rt_err::#enum_::#interrupt, #priority))
+    }
+}
+
 /// Generates an identifier for the `INPUTS` buffer (`spawn` & `schedule` API)
 pub fn inputs_ident(task: &Ident) -> Ident {
     Ident::new(&format!("{}_INPUTS", task), Span::call_site())
@@ -89,11 +271,6 @@ pub fn interrupt_ident() -> Ident {
     Ident::new("interrupt", span)
 }
 
-pub fn timer_queue_marker_ident() -> Ident {
-    let span = Span::call_site();
-    Ident::new("TIMER_QUEUE_MARKER", span)
-}
-
 /// Whether `name` is an exception with configurable priority
 pub fn is_exception(name: &Ident) -> bool {
     let s = name.to_string();
@@ -238,14 +415,292 @@ pub fn resources_ident(ctxt: Context, app: &App) -> Ident {
     Ident::new(&s, Span::call_site())
 }
 
+/// Generates an identifier for the keys of the last cancelled instances of a task, per slot
+pub fn monotonic_cancelled_ident(task: &Ident, monotonic: &Ident) -> Ident {
+    Ident::new(
+        &format!("{}_{}_CANCELLED", task, monotonic),
+        Span::call_site(),
+    )
+}
+
+/// Generates an identifier for the instance of a task tracked by `spawn_or_reschedule_after`
+pub fn monotonic_outstanding_ident(task: &Ident, monotonic: &Ident) -> Ident {
+    Ident::new(
+        &format!("{}_{}_OUTSTANDING", task, monotonic),
+        Span::call_site(),
+    )
+}
+
+/// Generates an identifier for the timer queue and ready queue keys of the last released instance
+/// in each slot of a task
+pub fn monotonic_released_ident(task: &Ident, monotonic: &Ident) -> Ident {
+    Ident::new(
+        &format!("{}_{}_RELEASED", task, monotonic),
+        Span::call_site(),
+    )
+}
+
+/// Generates an identifier for the state of a periodic task
+///
+/// Holds the free queue slot and the timer queue key of the next release while the task runs
+pub fn periodic_ident(task: &Ident) -> Ident {
+    Ident::new(&format!("{}_PERIODIC", task), Span::call_site())
+}
+
+/// Generates an identifier for the future slots of an `async` task, one per instance
+pub fn futures_ident(task: &Ident) -> Ident {
+    Ident::new(&format!("{}_FUTURES", task), Span::call_site())
+}
+
+/// Whether any software task is `async`
+///
+/// Only then do the timer queues carry delays.
+pub fn has_async_tasks(app: &App, extra: &Extra) -> bool {
+    app.software_tasks
+        .keys()
+        .any(|name| extra.tasks[name].is_async)
+}
+
+/// The hidden `SCHED_T` variant of a `Delay`, holding a pointer to the waker the timer queue
+/// handler wakes
+pub fn delay_variant_ident() -> Ident {
+    mark_internal_name("delay")
+}
+
+/// Generates an identifier for the ready queue key of the instance spawned by `spawn_unique`
+pub fn unique_ident(task: &Ident) -> Ident {
+    Ident::new(&format!("{}_UNIQUE", task), Span::call_site())
+}
+
+/// Generates an identifier for the queue of a channel
+pub fn channel_ident(channel: &Ident) -> Ident {
+    Ident::new(&format!("CHANNEL_{}", channel), Span::call_site())
+}
+
+/// The ceiling of a channel: the highest priority among the tasks that send to or receive from it
+pub fn channel_ceiling(app: &App, extra: &Extra, channel: &Ident) -> u8 {
+    extra
+        .tasks
+        .iter()
+        .filter(|(_, args)| args.senders.contains(channel) || args.receivers.contains(channel))
+        .map(|(name, _)| {
+            if let Some(task) = app.hardware_tasks.get(name) {
+                task.args.priority
+            } else if let Some(task) = app.software_tasks.get(name) {
+                task.args.priority
+            } else {
+                // `#[idle]`
+                0
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Generates an identifier for the `index`-th `#[pool]`
+pub fn pool_ident(index: usize) -> Ident {
+    Ident::new(&format!("POOL{}", index), Span::call_site())
+}
+
+/// Generates an identifier for the memory of the `index`-th `#[pool]`
+pub fn pool_memory_ident(index: usize) -> Ident {
+    Ident::new(&format!("POOL{}_MEMORY", index), Span::call_site())
+}
+
+/// The type of the values of a task whose only message is a `Box` of a `#[pool]`
+pub fn pooled_message<'a>(extra: &'a Extra, inputs: &[PatType]) -> Option<&'a Type> {
+    let path = match inputs {
+        [input] => match &*input.ty {
+            Type::Path(ty) if ty.qself.is_none() => &ty.path,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    // Spelled `Box` (imported from `rtic`) or `rtic::Box`; other boxes, like
+    // `alloc::boxed::Box`, aren't allocated from a pool
+    let segments = path.segments.iter().collect::<Vec<_>>();
+    let segment = match &segments[..] {
+        [segment] if path.leading_colon.is_none() => segment,
+        [krate, segment] if krate.ident == "rtic" && krate.arguments.is_empty() => segment,
+        _ => return None,
+    };
+    if segment.ident != "Box" {
+        return None;
+    }
+
+    let inner = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => ty.to_token_stream().to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    extra
+        .pools
+        .iter()
+        .find(|pool| pool.ty.to_token_stream().to_string() == inner)
+        .map(|pool| &pool.ty)
+}
+
+/// Generates an identifier for the lateness statistics of a task with a `max_latency`
+pub fn lateness_ident(task: &Ident) -> Ident {
+    Ident::new(&format!("{}_LATENESS", task), Span::call_site())
+}
+
+/// Generates an identifier for the execution time statistics of a task with a budget
+pub fn execution_time_ident(task: &Ident) -> Ident {
+    Ident::new(&format!("{}_EXECUTION_TIME", task), Span::call_site())
+}
+
+/// Wraps `run`, one run of `task`, in the measurement of its execution time if it has a budget
+///
+/// Expands to `run` alone for tasks without a budget
+pub fn measure_budget(app: &App, extra: &Extra, task: &Ident, run: TokenStream2) -> TokenStream2 {
+    let args = &extra.tasks[task];
+
+    let (start, time, budget) = if let Some(budget) = &args.budget {
+        let m = &default_monotonic(app)
+            .expect("RTIC-ICE: default monotonic")
+            .ident;
+
+        (
+            quote!(monotonics::#m::now()),
+            quote!(monotonics::#m::now()
+                .checked_duration_since(&start)
+                .map(|time| time.integer().into())
+                .unwrap_or(0)),
+            quote!((rtic::time::Instant::<#m>::new(0) + #budget)
+                .duration_since_epoch()
+                .integer()
+                .into()),
+        )
+    } else if let Some(cycles) = &args.budget_cycles {
+        (
+            quote!(rtic::export::budget_cycles!()),
+            quote!(rtic::export::budget_cycles!().wrapping_sub(start).into()),
+            quote!({
+                let cycles: u32 = #cycles;
+                cycles.into()
+            }),
+        )
+    } else {
+        return run;
+    };
+
+    let stats = mark_internal_ident(&execution_time_ident(task));
+    let name_s = task.to_string();
+    let on_overrun = extra.budget_overrun.iter();
+
+    quote!(
+        let start = #start;
+        #run;
+        let time: u64 = #time;
+        let budget: u64 = #budget;
+
+        if rtic::export::interrupt::free(|_| #stats.get_mut_unchecked().record(time, budget)) {
+            #(#on_overrun(#name_s, time);)*
+        }
+    )
+}
+
+/// The tasks a `Tracer` is told about, in the order of their IDs: the hardware tasks, the
+/// software tasks, `#[idle]` and the timer queue handlers, which are named after their monotonic
+pub fn traced_tasks(app: &App) -> Vec<&Ident> {
+    app.hardware_tasks
+        .keys()
+        .chain(app.software_tasks.keys())
+        .chain(app.idles.iter().map(|idle| &idle.name))
+        .chain(app.monotonics.values().map(|monotonic| &monotonic.ident))
+        .collect()
+}
+
+/// The ID of `task`, or of the timer queue handler of the monotonic `task`
+pub fn task_id(app: &App, task: &Ident) -> u16 {
+    traced_tasks(app)
+        .iter()
+        .position(|name| *name == task)
+        .expect("RTIC-ICE: task ID") as u16
+}
+
+/// Wraps `run` in the `task_enter`/`task_exit` hooks of the tracer, if there's one
+pub fn trace(app: &App, extra: &Extra, task: &Ident, run: TokenStream2) -> TokenStream2 {
+    if let Some(tracer) = &extra.tracer {
+        let id = task_id(app, task);
+
+        quote!(
+            <#tracer as rtic::Tracer>::task_enter(#id);
+            {
+                #run
+            }
+            <#tracer as rtic::Tracer>::task_exit(#id);
+        )
+    } else {
+        run
+    }
+}
+
 /// Generates an identifier for a ready queue
 ///
 /// There may be several task dispatchers, one for each priority level.
-/// The ready queues are SPSC queues
+/// The ready queues are linked lists so that entries can be cancelled before they're dispatched
 pub fn rq_ident(priority: u8) -> Ident {
     Ident::new(&format!("P{}_RQ", priority), Span::call_site())
 }
 
+/// Generates the code that enqueues the released instance `index` of `task` into the ready queue
+/// of priority `level`
+///
+/// Evaluates to the key of the new entry. `deadline` is only evaluated by `#[edf]` dispatchers.
+/// Must run inside a critical section
+pub fn rq_enqueue(
+    app: &App,
+    analysis: &Analysis,
+    extra: &Extra,
+    level: u8,
+    task: &Ident,
+    deadline: TokenStream2,
+) -> TokenStream2 {
+    let rq = mark_internal_ident(&rq_ident(level));
+    let t = spawn_t_ident(level);
+
+    let enqueue = if analysis.edf.contains(&level) {
+        quote!((&mut *#rq.get_mut_unchecked().as_mut_ptr()).enqueue_unchecked(#deadline, #t::#task, index))
+    } else {
+        quote!((&mut *#rq.get_mut_unchecked().as_mut_ptr()).enqueue_unchecked(#t::#task, index))
+    };
+
+    let record = stats_record(
+        &rq_ident(level),
+        quote!((&*#rq.get_unchecked().as_ptr()).0.len()),
+    );
+    let trace = extra.tracer.as_ref().map(|tracer| {
+        let id = task_id(app, task);
+
+        quote!(<#tracer as rtic::Tracer>::spawn(#id);)
+    });
+
+    if cfg!(feature = "stats") || trace.is_some() {
+        quote!({
+            let key = #enqueue;
+            #record
+            #trace
+
+            key
+        })
+    } else {
+        enqueue
+    }
+}
+
+/// Generates an identifier for the priority that the `async` tasks of a priority level run at
+///
+/// Their futures outlive every run of the dispatcher, so it can't live on its stack.
+pub fn priority_ident(priority: u8) -> Ident {
+    Ident::new(&format!("P{}_PRIORITY", priority), Span::call_site())
+}
+
 /// Generates an identifier for the `enum` of `schedule`-able tasks
 pub fn schedule_t_ident() -> Ident {
     Ident::new(&"SCHED_T", Span::call_site())
@@ -270,6 +725,30 @@ pub fn tq_ident(name: &str) -> Ident {
     Ident::new(&format!("TQ_{}", name), Span::call_site())
 }
 
+/// The number of entries of a timer queue
+///
+/// There's room for every instance of every software task, plus one `Delay` per instance of an
+/// `async` task.
+pub fn tq_capacity(app: &App, extra: &Extra) -> usize {
+    app.software_tasks
+        .iter()
+        .map(|(name, task)| {
+            let capacity = usize::from(task.args.capacity);
+
+            if extra.tasks[name].is_async {
+                2 * capacity
+            } else {
+                capacity
+            }
+        })
+        .sum()
+}
+
+/// Generates an identifier for the timer queue operations of the `Delay`s of a monotonic
+pub fn delay_ops_ident(name: &str) -> Ident {
+    Ident::new(&format!("DELAY_OPS_{}", name), Span::call_site())
+}
+
 /// Generates an identifier for monotonic timer storage
 pub fn monotonic_ident(name: &str) -> Ident {
     Ident::new(&format!("MONOTONIC_STORAGE_{}", name), Span::call_site())
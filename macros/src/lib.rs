@@ -3,13 +3,17 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use std::{fs, path::Path};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use std::{collections::HashSet, fs, path::Path};
 
-use rtic_syntax::Settings;
+use rtic_syntax::{analyze::Analysis, ast::App, Settings};
+use syn::parse;
 
 mod analyze;
 mod check;
 mod codegen;
+mod syntax;
 #[cfg(test)]
 mod tests;
 
@@ -19,18 +23,121 @@ mod tests;
 
 #[proc_macro_attribute]
 pub fn app(args: TokenStream, input: TokenStream) -> TokenStream {
+    let (args, input) = (TokenStream2::from(args), TokenStream2::from(input));
+
+    match syntax::auto_dispatchers(&args) {
+        Err(e) => e.to_compile_error().into(),
+        // Only the device crate knows which of its interrupts are spare; it hands them back to
+        // `auto_dispatchers` along with the application
+        Ok(Some(device)) => quote!(
+            #device::rtic_spare_interrupts! {
+                rtic::export::auto_dispatchers, (#args) #input
+            }
+        )
+        .into(),
+        Ok(None) => expand(args, input).into(),
+    }
+}
+
+/// Second half of `#[app(dispatchers = auto)]`, invoked by the device crate's
+/// `rtic_spare_interrupts!` as `auto_dispatchers!([<spare interrupts>] (<app args>) <app>)`
+#[doc(hidden)]
+#[proc_macro]
+pub fn auto_dispatchers(input: TokenStream) -> TokenStream {
+    let (spare, args, input) = match syntax::spare_interrupts(input.into()) {
+        Err(e) => return e.to_compile_error().into(),
+        Ok(x) => x,
+    };
+
+    // Look at the application without dispatchers to learn which interrupts it binds and how many
+    // priority levels of software tasks it has
+    let app = match syntax::with_dispatchers(args.clone(), &[])
+        .and_then(|args| parse(args, input.clone()))
+    {
+        Err(e) => return e.to_compile_error().into(),
+        Ok((app, _, _)) => app,
+    };
+
+    let bound = app
+        .hardware_tasks
+        .values()
+        .map(|task| &task.args.binds)
+        .chain(
+            app.monotonics
+                .values()
+                .map(|monotonic| &monotonic.args.binds),
+        )
+        .collect::<HashSet<_>>();
+    let free = spare
+        .into_iter()
+        .filter(|interrupt| !bound.contains(interrupt))
+        .collect::<Vec<_>>();
+    let need = app
+        .software_tasks
+        .values()
+        .map(|task| task.args.priority)
+        .collect::<HashSet<_>>()
+        .len();
+
+    if need > free.len() {
+        // `auto` never multiplexes; point to the explicit list that does
+        let example = match free.split_last() {
+            Some((last, rest)) => format!(
+                ", e.g. `dispatchers = [{}#[multiplex] {}]`",
+                rest.iter()
+                    .map(|interrupt| format!("{}, ", interrupt))
+                    .collect::<String>(),
+                last
+            ),
+            None => String::new(),
+        };
+
+        return parse::Error::new(
+            Span::call_site(),
+            format!(
+                "not enough spare interrupts to dispatch all software tasks \
+                 (need: {}; spare and not bound by any task or monotonic: {}); list the \
+                 dispatchers and mark the last one `#[multiplex]` to have it serve several \
+                 priority levels{}",
+                need,
+                free.len(),
+                example
+            ),
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    match syntax::with_dispatchers(args, &free[..need]) {
+        Err(e) => e.to_compile_error().into(),
+        Ok(args) => expand(args, input).into(),
+    }
+}
+
+/// Strips the port specific extensions and parses the application
+fn parse(
+    args: TokenStream2,
+    input: TokenStream2,
+) -> parse::Result<(App, Analysis, syntax::Extensions)> {
     let mut settings = Settings::default();
     settings.optimize_priorities = false;
     settings.parse_binds = true;
     settings.parse_extern_interrupt = true;
 
-    let (app, analysis) = match rtic_syntax::parse(args, input, settings) {
-        Err(e) => return e.to_compile_error().into(),
+    let (args, input, extensions) = syntax::app(args, input)?;
+    let (app, analysis) = rtic_syntax::parse2(args, input, settings)?;
+
+    Ok((app, analysis, extensions))
+}
+
+fn expand(args: TokenStream2, input: TokenStream2) -> TokenStream2 {
+    let (app, analysis, extensions) = match parse(args, input) {
+        Err(e) => return e.to_compile_error(),
         Ok(x) => x,
     };
 
-    let extra = match check::app(&app, &analysis) {
-        Err(e) => return e.to_compile_error().into(),
+    let extra = match check::app(&app, &analysis, extensions) {
+        Err(e) => return e.to_compile_error(),
         Ok(x) => x,
     };
 
@@ -43,5 +150,5 @@ pub fn app(args: TokenStream, input: TokenStream) -> TokenStream {
         fs::write("target/rtic-expansion.rs", ts.to_string()).ok();
     }
 
-    ts.into()
+    ts
 }
@@ -0,0 +1,614 @@
+//! Cortex-M port specific extensions to the syntax accepted by `rtic-syntax`
+//!
+//! `rtic-syntax` rejects arguments it doesn't know about, so the extensions are stripped from the
+//! input here, before it's handed over to `rtic_syntax::parse`, and passed on to `check` on the
+//! side.
+
+use std::{collections::HashMap, mem};
+
+use proc_macro2::{Delimiter, Group, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    ext::IdentExt,
+    parse::{self, Parser},
+    punctuated::Punctuated,
+    Attribute, Expr, ExprArray, Fields, FnArg, ForeignItem, Ident, Item, ItemMod, ItemStruct,
+    LitInt, Path, Signature, Token, Type,
+};
+
+/// Port specific arguments of a `#[task]`
+#[derive(Default)]
+pub struct TaskArgs {
+    /// `period = <duration>`: re-spawn the task at this rate on the default monotonic
+    pub period: Option<Expr>,
+    /// `offset = <duration>`: delay of the first release of a periodic task
+    pub offset: Option<Expr>,
+    /// `max_latency = <duration>`: bound on the lateness of releases from the timer queue
+    pub max_latency: Option<Expr>,
+    /// `budget = <duration>`: execution time budget, measured on the default monotonic
+    pub budget: Option<Expr>,
+    /// `budget_cycles = <u32>`: execution time budget, measured with the DWT cycle counter
+    pub budget_cycles: Option<Expr>,
+    /// `overflow = <policy>`: what `spawn` does when all the `capacity` slots are in use
+    pub overflow: Option<Overflow>,
+    /// `async fn`: the task is a future, polled by the dispatcher of its priority level
+    pub is_async: bool,
+    /// `senders = [..]`: channels the task sends to
+    pub senders: Vec<Ident>,
+    /// `receivers = [..]`: channels the task receives from
+    pub receivers: Vec<Ident>,
+    /// `by_ref`: the task borrows its messages from the message buffer instead of taking them
+    pub by_ref: bool,
+    /// The reference types of the messages of a `by_ref` task, as written by the user
+    pub ref_types: Vec<Type>,
+}
+
+/// Policy of a `spawn` that finds no free slot
+#[derive(Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// `error`: fail with `SpawnError::Full`, the default
+    Error,
+    /// `drop_oldest`: evict the oldest message waiting in the ready queue
+    DropOldest,
+    /// `replace_latest`: overwrite the newest message waiting in the ready queue
+    ReplaceLatest,
+}
+
+impl TaskArgs {
+    /// Whether the execution time of the task is measured
+    pub fn has_budget(&self) -> bool {
+        self.budget.is_some() || self.budget_cycles.is_some()
+    }
+}
+
+/// A field of the `#[channels]` struct
+pub struct Channel {
+    pub name: Ident,
+    /// Type of the messages
+    pub ty: Type,
+    /// `capacity = <u8>`: how many messages the channel holds, 1 by default
+    pub capacity: u8,
+    /// `spawn = <task>`: software task spawned, with `spawn_unique`, after every send
+    pub spawn: Option<Ident>,
+}
+
+/// A `#[pool(T, N)]` attribute of the app module
+pub struct Pool {
+    /// Type of the values the blocks hold
+    pub ty: Type,
+    /// Number of blocks
+    pub capacity: usize,
+}
+
+/// The port specific extensions found in the input
+pub struct Extensions {
+    /// `deadline_miss = <path>`: called when a task is released later than its `max_latency`
+    pub deadline_miss: Option<Path>,
+    /// `budget_overrun = <path>`: called when a task runs for longer than its budget
+    pub budget_overrun: Option<Path>,
+    /// `tracer = <path>`: type whose `rtic::Tracer` hooks are called when tasks run
+    pub tracer: Option<Path>,
+    /// Arguments of every `#[task]` (and of `#[idle]`), keyed by task name
+    pub tasks: HashMap<Ident, TaskArgs>,
+    /// The fields of the `#[channels]` struct
+    pub channels: Vec<Channel>,
+    /// The memory pools declared on the app module
+    pub pools: Vec<Pool>,
+}
+
+/// Strips the extensions from `args` and `input`
+pub fn app(
+    args: TokenStream2,
+    input: TokenStream2,
+) -> parse::Result<(TokenStream2, TokenStream2, Extensions)> {
+    let mut extensions = Extensions {
+        deadline_miss: None,
+        budget_overrun: None,
+        tracer: None,
+        tasks: HashMap::new(),
+        channels: vec![],
+        pools: vec![],
+    };
+
+    let args = app_args(args, &mut extensions)?;
+
+    // Leave malformed input to `rtic-syntax`, which has better error messages
+    let mut item: ItemMod = match syn::parse2(input.clone()) {
+        Ok(item) => item,
+        Err(_) => return Ok((args, input, extensions)),
+    };
+
+    // Nor does it know about pools, which are declared on the module itself
+    let mut attrs = vec![];
+    for attr in mem::replace(&mut item.attrs, vec![]) {
+        if attr.path.is_ident("pool") {
+            extensions.pools.push(attr.parse_args_with(pool)?);
+        } else {
+            attrs.push(attr);
+        }
+    }
+    item.attrs = attrs;
+
+    if let Some((_, items)) = &mut item.content {
+        // `rtic-syntax` doesn't know about channels at all
+        let mut channels = None;
+        for (i, item) in items.iter().enumerate() {
+            if let Item::Struct(s) = item {
+                if s.attrs.iter().any(|attr| attr.path.is_ident("channels")) {
+                    if channels.is_some() {
+                        return Err(parse::Error::new(
+                            s.ident.span(),
+                            "`#[channels]` struct must appear at most once",
+                        ));
+                    }
+
+                    channels = Some(i);
+                }
+            }
+        }
+
+        if let Some(i) = channels {
+            if let Item::Struct(s) = items.remove(i) {
+                extensions.channels = channels_struct(s)?;
+            }
+        }
+
+        for item in items {
+            match item {
+                Item::Fn(f) => {
+                    if let Some(mut args) = task_args(&mut f.attrs)? {
+                        // `rtic-syntax` only accepts plain functions; codegen makes them `async`
+                        // again
+                        args.is_async = f.sig.asyncness.take().is_some();
+                        strip_refs(&mut f.sig, &mut args)?;
+                        extensions.tasks.insert(f.sig.ident.clone(), args);
+                    }
+                }
+
+                Item::ForeignMod(m) => {
+                    for item in &mut m.items {
+                        if let ForeignItem::Fn(f) = item {
+                            if let Some(mut args) = task_args(&mut f.attrs)? {
+                                strip_refs(&mut f.sig, &mut args)?;
+                                extensions.tasks.insert(f.sig.ident.clone(), args);
+                            }
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    Ok((args, item.into_token_stream(), extensions))
+}
+
+/// Returns the `device` path if the app asks for `dispatchers = auto`
+pub fn auto_dispatchers(args: &TokenStream2) -> parse::Result<Option<Path>> {
+    // Leave malformed arguments to `rtic-syntax`
+    let args = match split_args(args.clone()) {
+        Ok(args) => args,
+        Err(_) => return Ok(None),
+    };
+
+    let auto = args.iter().any(|(key, value)| {
+        key == "dispatchers"
+            && value.as_ref().map_or(false, |value| {
+                syn::parse2::<Ident>(value.clone()).map_or(false, |value| value == "auto")
+            })
+    });
+
+    if !auto {
+        return Ok(None);
+    }
+
+    match args.into_iter().find(|(key, _)| key == "device") {
+        Some((key, value)) => parse_value(&key, value, false, "path").map(Some),
+        None => Err(parse::Error::new(
+            Span::call_site(),
+            "`dispatchers = auto` picks the spare interrupts of the `device`; \
+             a `device` argument must be specified in `#[rtic::app]`",
+        )),
+    }
+}
+
+/// Splits the input of `auto_dispatchers!` into the spare interrupts of the device, the app
+/// arguments and the app
+pub fn spare_interrupts(
+    input: TokenStream2,
+) -> parse::Result<(Vec<Ident>, TokenStream2, TokenStream2)> {
+    let mut tokens = input.into_iter();
+
+    let spare = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => {
+            let spare: Punctuated<Ident, Token![,]> =
+                Punctuated::parse_terminated.parse2(group.stream())?;
+
+            spare.into_iter().collect()
+        }
+        _ => {
+            return Err(parse::Error::new(
+                Span::call_site(),
+                "expected the list of spare interrupts, `[..]`",
+            ))
+        }
+    };
+
+    let args = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream()
+        }
+        _ => {
+            return Err(parse::Error::new(
+                Span::call_site(),
+                "expected the application arguments, `(..)`",
+            ))
+        }
+    };
+
+    Ok((spare, args, tokens.collect()))
+}
+
+/// Replaces the value of the `dispatchers` argument with a list of `dispatchers`
+pub fn with_dispatchers(args: TokenStream2, dispatchers: &[Ident]) -> parse::Result<TokenStream2> {
+    let args = split_args(args)?.into_iter().map(|(key, value)| {
+        if key == "dispatchers" {
+            quote!(dispatchers = [#(#dispatchers),*])
+        } else {
+            value.map_or_else(|| quote!(#key), |value| quote!(#key = #value))
+        }
+    });
+
+    Ok(quote!(#(#args),*))
+}
+
+/// Strips the port specific arguments from the `#[rtic::app(..)]` arguments
+fn app_args(args: TokenStream2, extensions: &mut Extensions) -> parse::Result<TokenStream2> {
+    // Leave malformed arguments to `rtic-syntax` as well
+    let args = match split_args(args.clone()) {
+        Ok(args) => args,
+        Err(_) => return Ok(args),
+    };
+
+    let mut kept = vec![];
+    for (key, value) in args {
+        match &*key.to_string() {
+            "deadline_miss" => {
+                extensions.deadline_miss = Some(parse_value(
+                    &key,
+                    value,
+                    extensions.deadline_miss.is_some(),
+                    "path",
+                )?)
+            }
+            "budget_overrun" => {
+                extensions.budget_overrun = Some(parse_value(
+                    &key,
+                    value,
+                    extensions.budget_overrun.is_some(),
+                    "path",
+                )?)
+            }
+            "tracer" => {
+                extensions.tracer = Some(parse_value(
+                    &key,
+                    value,
+                    extensions.tracer.is_some(),
+                    "path",
+                )?)
+            }
+            _ => kept.push(value.map_or_else(|| quote!(#key), |value| quote!(#key = #value))),
+        }
+    }
+
+    Ok(quote!(#(#kept),*))
+}
+
+/// Parses the fields of the `#[channels]` struct
+fn channels_struct(s: ItemStruct) -> parse::Result<Vec<Channel>> {
+    let fields = match s.fields {
+        Fields::Named(fields) => fields.named,
+        _ => {
+            return Err(parse::Error::new(
+                s.ident.span(),
+                "the `#[channels]` struct must have named fields",
+            ))
+        }
+    };
+
+    let mut channels = vec![];
+    for field in fields {
+        let name = field.ident.expect("UNREACHABLE");
+        let mut channel = Channel {
+            name,
+            ty: field.ty,
+            capacity: 1,
+            spawn: None,
+        };
+
+        for attr in field.attrs {
+            if attr.path.is_ident("doc") {
+                continue;
+            }
+
+            if !attr.path.is_ident("channel") {
+                return Err(parse::Error::new(
+                    channel.name.span(),
+                    "only `#[channel(..)]` and doc comments are accepted on channels",
+                ));
+            }
+
+            let inner = match attr.tokens.into_iter().next() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    group.stream()
+                }
+                _ => continue,
+            };
+
+            let mut capacity = None;
+            for (key, value) in split_args(inner)? {
+                match &*key.to_string() {
+                    "capacity" => {
+                        let lit: LitInt = parse_value(&key, value, capacity.is_some(), "u8")?;
+                        let value = lit.base10_parse::<u8>()?;
+
+                        if value == 0 {
+                            return Err(parse::Error::new(
+                                lit.span(),
+                                "this channel can't hold any message",
+                            ));
+                        }
+
+                        capacity = Some(value);
+                    }
+                    "spawn" => {
+                        channel.spawn =
+                            Some(parse_value(&key, value, channel.spawn.is_some(), "task")?)
+                    }
+                    _ => {
+                        return Err(parse::Error::new(
+                            key.span(),
+                            "expected `capacity` or `spawn`",
+                        ))
+                    }
+                }
+            }
+
+            if let Some(capacity) = capacity {
+                channel.capacity = capacity;
+            }
+        }
+
+        channels.push(channel);
+    }
+
+    Ok(channels)
+}
+
+/// Parses the arguments of a `#[pool(T, N)]` attribute
+fn pool(input: parse::ParseStream) -> parse::Result<Pool> {
+    let ty = input.parse()?;
+    let _: Token![,] = input.parse()?;
+    let lit: LitInt = input.parse()?;
+    let capacity = lit.base10_parse::<usize>()?;
+    let _: Option<Token![,]> = input.parse()?;
+
+    if capacity == 0 {
+        return Err(parse::Error::new(lit.span(), "this pool has no blocks"));
+    }
+
+    Ok(Pool { ty, capacity })
+}
+
+/// Strips the port specific arguments from a `#[task(..)]` (or `#[idle(..)]`) attribute, if
+/// there's one
+fn task_args(attrs: &mut [Attribute]) -> parse::Result<Option<TaskArgs>> {
+    let attr = match attrs
+        .iter_mut()
+        .find(|attr| attr.path.is_ident("task") || attr.path.is_ident("idle"))
+    {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+
+    let mut args = TaskArgs::default();
+
+    let inner = match attr.tokens.clone().into_iter().next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream()
+        }
+        _ => return Ok(Some(args)),
+    };
+
+    let mut kept = vec![];
+    let mut by_ref = None;
+    let mut binds = false;
+    for (key, value) in split_args(inner)? {
+        match &*key.to_string() {
+            "period" => args.period = Some(expr(&key, value, args.period.is_some())?),
+            "offset" => args.offset = Some(expr(&key, value, args.offset.is_some())?),
+            "max_latency" => {
+                args.max_latency = Some(expr(&key, value, args.max_latency.is_some())?)
+            }
+            "budget" => args.budget = Some(expr(&key, value, args.budget.is_some())?),
+            "budget_cycles" => {
+                args.budget_cycles = Some(expr(&key, value, args.budget_cycles.is_some())?)
+            }
+            "overflow" => args.overflow = Some(overflow(&key, value, args.overflow.is_some())?),
+            "by_ref" => {
+                if value.is_some() || args.by_ref {
+                    return Err(parse::Error::new(
+                        key.span(),
+                        "expected `by_ref` once, alone",
+                    ));
+                }
+
+                args.by_ref = true;
+                by_ref = Some(key.span());
+            }
+            "senders" => args.senders = idents(&key, value, !args.senders.is_empty())?,
+            "receivers" => args.receivers = idents(&key, value, !args.receivers.is_empty())?,
+            _ => {
+                binds |= key == "binds";
+                kept.push(value.map_or_else(|| quote!(#key), |value| quote!(#key = #value)))
+            }
+        }
+    }
+
+    // Hardware tasks and `#[idle]` take no messages to borrow; `rtic-syntax` would reject the
+    // signature `strip_refs` leaves them with
+    if let Some(span) = by_ref {
+        if binds || attr.path.is_ident("idle") {
+            return Err(parse::Error::new(
+                span,
+                "`by_ref` can only be used on software tasks",
+            ));
+        }
+    }
+
+    attr.tokens = if kept.is_empty() {
+        TokenStream2::new()
+    } else {
+        Group::new(Delimiter::Parenthesis, quote!(#(#kept),*)).into_token_stream()
+    };
+
+    Ok(Some(args))
+}
+
+/// Turns the `&T` / `&mut T` messages of a `by_ref` task into `T`, which is what's stored in the
+/// message buffer
+fn strip_refs(sig: &mut Signature, args: &mut TaskArgs) -> parse::Result<()> {
+    if !args.by_ref {
+        return Ok(());
+    }
+
+    // The first input is the context
+    if sig.inputs.len() < 2 {
+        return Err(parse::Error::new(
+            sig.ident.span(),
+            "`by_ref` tasks must take at least one message",
+        ));
+    }
+
+    for input in sig.inputs.iter_mut().skip(1) {
+        let ty = match input {
+            FnArg::Typed(input) => &mut input.ty,
+            FnArg::Receiver(_) => continue,
+        };
+
+        let elem = match &**ty {
+            Type::Reference(reference) if reference.lifetime.is_none() => (*reference.elem).clone(),
+            Type::Reference(reference) => {
+                return Err(parse::Error::new_spanned(
+                    &reference.lifetime,
+                    "`by_ref` messages are only borrowed while the task runs; \
+                     leave the lifetime out",
+                ))
+            }
+            _ => {
+                return Err(parse::Error::new_spanned(
+                    ty,
+                    "`by_ref` tasks take their messages as `&T` or `&mut T`",
+                ))
+            }
+        };
+
+        args.ref_types.push(mem::replace(&mut **ty, elem));
+    }
+
+    Ok(())
+}
+
+/// Splits a comma separated `key [= value]` list
+///
+/// Values are parsed as expressions, so commas inside them (e.g. in generic arguments) don't end
+/// them early.
+fn split_args(tokens: TokenStream2) -> parse::Result<Vec<(Ident, Option<TokenStream2>)>> {
+    let parser = |input: parse::ParseStream<'_>| {
+        let mut args = vec![];
+
+        while !input.is_empty() {
+            let key = Ident::parse_any(input)?;
+
+            let value = if input.peek(Token![=]) {
+                input.parse::<Token![=]>()?;
+
+                if input.is_empty() || input.peek(Token![,]) {
+                    return Err(parse::Error::new(key.span(), "expected a value"));
+                }
+
+                Some(input.parse::<Expr>()?.into_token_stream())
+            } else {
+                None
+            };
+
+            args.push((key, value));
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(args)
+    };
+
+    parser.parse2(tokens)
+}
+
+fn expr(key: &Ident, value: Option<TokenStream2>, duplicate: bool) -> parse::Result<Expr> {
+    parse_value(key, value, duplicate, "expression")
+}
+
+/// Parses a `[a, b, ..]` list
+fn idents(key: &Ident, value: Option<TokenStream2>, duplicate: bool) -> parse::Result<Vec<Ident>> {
+    let list: ExprArray = parse_value(key, value, duplicate, "[..]")?;
+
+    list.elems
+        .into_iter()
+        .map(|elem| match elem {
+            Expr::Path(path) if path.attrs.is_empty() && path.qself.is_none() => {
+                path.path.get_ident().cloned().ok_or_else(|| {
+                    parse::Error::new(path.path.segments[0].ident.span(), "expected a channel")
+                })
+            }
+            elem => Err(parse::Error::new_spanned(elem, "expected a channel")),
+        })
+        .collect()
+}
+
+fn overflow(key: &Ident, value: Option<TokenStream2>, duplicate: bool) -> parse::Result<Overflow> {
+    let policy: Ident = parse_value(key, value, duplicate, "policy")?;
+
+    match &*policy.to_string() {
+        "error" => Ok(Overflow::Error),
+        "drop_oldest" => Ok(Overflow::DropOldest),
+        "replace_latest" => Ok(Overflow::ReplaceLatest),
+        _ => Err(parse::Error::new(
+            policy.span(),
+            "expected `drop_oldest`, `replace_latest` or `error`",
+        )),
+    }
+}
+
+fn parse_value<T: parse::Parse>(
+    key: &Ident,
+    value: Option<TokenStream2>,
+    duplicate: bool,
+    what: &str,
+) -> parse::Result<T> {
+    if duplicate {
+        return Err(parse::Error::new(
+            key.span(),
+            format!("argument `{}` was already specified", key),
+        ));
+    }
+
+    match value {
+        Some(value) => syn::parse2(value),
+        None => Err(parse::Error::new(
+            key.span(),
+            format!("expected `{} = <{}>`", key, what),
+        )),
+    }
+}
@@ -1,4 +1,4 @@
-use quote::quote;
+use quote::{quote, ToTokens};
 use rtic_syntax::Settings;
 
 #[test]
@@ -27,3 +27,176 @@ fn analyze() {
     assert_eq!(interrupts[&2].0.to_string(), "B");
     assert_eq!(interrupts[&1].0.to_string(), "A");
 }
+
+#[test]
+fn analyze_multiplex() {
+    let mut settings = Settings::default();
+    settings.parse_extern_interrupt = true;
+    let (app, analysis) = rtic_syntax::parse2(
+        // The `#[multiplex]` dispatcher also serves the levels that are left over
+        quote!(
+            device = pac,
+            dispatchers = [
+                B,
+                #[multiplex]
+                A
+            ]
+        ),
+        quote!(
+            mod app {
+                #[task(priority = 1)]
+                fn a(_: a::Context) {}
+
+                #[task(priority = 2)]
+                fn b(_: b::Context) {}
+
+                #[task(priority = 4)]
+                fn c(_: c::Context) {}
+            }
+        ),
+        settings,
+    )
+    .unwrap();
+
+    let analysis = crate::analyze::app(analysis, &app);
+    let interrupts = &analysis.interrupts;
+    assert_eq!(interrupts.len(), 3);
+    assert_eq!(interrupts[&4].0.to_string(), "B");
+    assert_eq!(interrupts[&2].0.to_string(), "A");
+    assert_eq!(interrupts[&1].0.to_string(), "A");
+    assert!(interrupts[&1].1.attrs.is_empty());
+    assert_eq!(analysis.multiplexed.iter().collect::<Vec<_>>(), [&1, &2]);
+}
+
+#[test]
+fn extensions() {
+    // Port specific task arguments are stripped before the input reaches `rtic-syntax`
+    let (args, input, extensions) = crate::syntax::app(
+        quote!(
+            device = pac,
+            dispatchers = [A],
+            deadline_miss = crate::on_miss,
+            budget_overrun = crate::on_overrun,
+            tracer = crate::Trace
+        ),
+        quote!(
+            #[pool(Frame, 4)]
+            mod app {
+                #[task(priority = 2, period = Seconds(1_u32), offset = Seconds(2_u32))]
+                fn a(_: a::Context) {}
+
+                #[task(period = Seconds(3_u32))]
+                fn b(_: b::Context) {}
+
+                #[task(max_latency = Milliseconds(5_u32), budget_cycles = 1_000)]
+                fn c(_: c::Context) {}
+
+                #[task(senders = [samples])]
+                async fn d(_: d::Context) {}
+
+                #[task(by_ref)]
+                fn e(_: e::Context, frame: &mut [u8; 64]) {}
+
+                #[channels]
+                struct Channels {
+                    #[channel(capacity = 4, spawn = c)]
+                    samples: u16,
+                }
+            }
+        ),
+    )
+    .unwrap();
+
+    let mut settings = Settings::default();
+    settings.parse_extern_interrupt = true;
+    let (app, _) = rtic_syntax::parse2(args, input, settings).unwrap();
+    assert_eq!(app.software_tasks.len(), 5);
+
+    let tasks = &extensions.tasks;
+    assert_eq!(tasks.len(), 5);
+    assert!(tasks.values().filter(|args| args.period.is_some()).count() == 2);
+    assert!(tasks.values().filter(|args| args.offset.is_some()).count() == 1);
+    assert!(
+        tasks
+            .values()
+            .filter(|args| args.max_latency.is_some())
+            .count()
+            == 1
+    );
+    assert!(tasks.values().filter(|args| args.has_budget()).count() == 1);
+    assert!(tasks.values().filter(|args| args.is_async).count() == 1);
+    assert!(
+        tasks
+            .values()
+            .filter(|args| args.senders.len() == 1)
+            .count()
+            == 1
+    );
+    assert!(tasks.values().filter(|args| args.by_ref).count() == 1);
+    assert_eq!(extensions.channels.len(), 1);
+    assert_eq!(extensions.channels[0].capacity, 4);
+    assert!(extensions.channels[0].spawn.is_some());
+    assert_eq!(extensions.pools.len(), 1);
+    assert_eq!(extensions.pools[0].capacity, 4);
+    assert!(extensions.deadline_miss.is_some());
+    assert!(extensions.budget_overrun.is_some());
+    assert!(extensions.tracer.is_some());
+}
+
+#[test]
+fn generic_durations() {
+    // Commas in generic arguments don't end a value
+    let (args, input, extensions) = crate::syntax::app(
+        quote!(device = pac, dispatchers = [A]),
+        quote!(
+            mod app {
+                #[task(
+                    period = Duration::<u32, 1, 1_000>::from_ticks(10),
+                    priority = 2,
+                    offset = Duration::<u32, 1, 1_000>::from_ticks(5)
+                )]
+                fn a(_: a::Context) {}
+            }
+        ),
+    )
+    .unwrap();
+
+    let mut settings = Settings::default();
+    settings.parse_extern_interrupt = true;
+    let (app, _) = rtic_syntax::parse2(args, input, settings).unwrap();
+    assert_eq!(app.software_tasks.values().next().unwrap().args.priority, 2);
+
+    let args = extensions.tasks.values().next().unwrap();
+    assert_eq!(
+        args.period.to_token_stream().to_string(),
+        quote!(Duration::<u32, 1, 1_000>::from_ticks(10)).to_string()
+    );
+    assert!(args.offset.is_some());
+}
+
+#[test]
+fn auto_dispatchers() {
+    let args = quote!(device = crate::pac, dispatchers = auto);
+    let device = crate::syntax::auto_dispatchers(&args).unwrap().unwrap();
+    assert_eq!(quote!(#device).to_string(), quote!(crate::pac).to_string());
+    assert!(
+        crate::syntax::auto_dispatchers(&quote!(device = pac, dispatchers = [A]))
+            .unwrap()
+            .is_none()
+    );
+
+    // As handed back by the device crate's `rtic_spare_interrupts!`
+    let app = quote!(
+        mod app {}
+    );
+    let (spare, args, input) =
+        crate::syntax::spare_interrupts(quote!([A, B, C] (#args) #app)).unwrap();
+    assert_eq!(spare.len(), 3);
+    assert_eq!(input.to_string(), app.to_string());
+
+    let args = crate::syntax::with_dispatchers(args, &spare[..2]).unwrap();
+    assert_eq!(
+        args.to_string(),
+        quote!(device = crate::pac, dispatchers = [A, B]).to_string()
+    );
+}
@@ -0,0 +1,161 @@
+//! Typed message channels between tasks
+//!
+//! A channel is a queue in a `static` that is shared by the tasks that send to it and the tasks
+//! that receive from it. Like a resource, it has a ceiling: the highest priority among those
+//! tasks. Sending and receiving lock the queue up to that ceiling.
+
+use core::marker::PhantomData;
+
+use crate::export::{lock, ArrayLength, ChannelQueue, Priority};
+
+/// Sending end of a channel, found in `<task>::Context.senders`
+pub struct Sender<'a, T, N>
+where
+    N: ArrayLength<T>,
+{
+    queue: *mut ChannelQueue<T, N>,
+    priority: &'a Priority,
+    capacity: u8,
+    ceiling: u8,
+    nvic_prio_bits: u8,
+    notify: Option<fn()>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T, N> Sender<'a, T, N>
+where
+    N: ArrayLength<T>,
+{
+    #[doc(hidden)]
+    #[inline(always)]
+    pub unsafe fn new(
+        queue: *mut ChannelQueue<T, N>,
+        priority: &'a Priority,
+        capacity: u8,
+        ceiling: u8,
+        nvic_prio_bits: u8,
+        notify: Option<fn()>,
+    ) -> Self {
+        Sender {
+            queue,
+            priority,
+            capacity,
+            ceiling,
+            nvic_prio_bits,
+            notify,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Sends `message`, handing it back if the channel is full
+    ///
+    /// If the channel spawns its receiving task, the task is spawned after the message has been
+    /// queued, unless an instance it spawned is still waiting to run.
+    pub fn send(&mut self, message: T) -> Result<(), T> {
+        let capacity = usize::from(self.capacity);
+
+        unsafe {
+            lock(
+                self.queue,
+                self.priority,
+                self.ceiling,
+                self.nvic_prio_bits,
+                |queue| {
+                    if usize::from(queue.len()) < capacity {
+                        queue.enqueue_unchecked(message);
+
+                        Ok(())
+                    } else {
+                        Err(message)
+                    }
+                },
+            )
+        }?;
+
+        if let Some(notify) = self.notify {
+            notify();
+        }
+
+        Ok(())
+    }
+
+    /// Whether a `send` would fail right now
+    pub fn is_full(&mut self) -> bool {
+        let capacity = usize::from(self.capacity);
+
+        unsafe {
+            lock(
+                self.queue,
+                self.priority,
+                self.ceiling,
+                self.nvic_prio_bits,
+                |queue| usize::from(queue.len()) >= capacity,
+            )
+        }
+    }
+}
+
+/// Receiving end of a channel, found in `<task>::Context.receivers`
+pub struct Receiver<'a, T, N>
+where
+    N: ArrayLength<T>,
+{
+    queue: *mut ChannelQueue<T, N>,
+    priority: &'a Priority,
+    ceiling: u8,
+    nvic_prio_bits: u8,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, T, N> Receiver<'a, T, N>
+where
+    N: ArrayLength<T>,
+{
+    #[doc(hidden)]
+    #[inline(always)]
+    pub unsafe fn new(
+        queue: *mut ChannelQueue<T, N>,
+        priority: &'a Priority,
+        ceiling: u8,
+        nvic_prio_bits: u8,
+    ) -> Self {
+        Receiver {
+            queue,
+            priority,
+            ceiling,
+            nvic_prio_bits,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Takes the oldest message out of the channel, if there's one
+    pub fn recv(&mut self) -> Option<T> {
+        unsafe {
+            lock(
+                self.queue,
+                self.priority,
+                self.ceiling,
+                self.nvic_prio_bits,
+                |queue| queue.dequeue(),
+            )
+        }
+    }
+
+    /// The number of messages waiting in the channel
+    pub fn len(&mut self) -> usize {
+        unsafe {
+            lock(
+                self.queue,
+                self.priority,
+                self.ceiling,
+                self.nvic_prio_bits,
+                |queue| usize::from(queue.len()),
+            )
+        }
+    }
+
+    /// Whether there's no message waiting in the channel
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+}
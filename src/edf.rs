@@ -0,0 +1,119 @@
+use crate::{
+    linked_list::{ArrayLength, Key, LinkedList, Min, Node},
+    time::Instant,
+    Monotonic,
+};
+use core::cmp::Ordering;
+
+/// Ready queue of a dispatcher that dispatches the earliest deadline first
+///
+/// Instances with equal deadlines are dispatched in the order they were released.
+pub struct EdfQueue<Mono, Task, N>(pub LinkedList<Ready<Mono, Task>, Min, N>)
+where
+    Mono: Monotonic,
+    N: ArrayLength<Node<Ready<Mono, Task>>>,
+    Task: Copy;
+
+impl<Mono, Task, N> EdfQueue<Mono, Task, N>
+where
+    Mono: Monotonic,
+    N: ArrayLength<Node<Ready<Mono, Task>>>,
+    Task: Copy,
+{
+    pub fn new() -> Self {
+        EdfQueue(LinkedList::new())
+    }
+
+    /// Enqueue a released task without checking if the queue is full
+    ///
+    /// # Safety
+    ///
+    /// The queue must not be full.
+    #[inline]
+    pub unsafe fn enqueue_unchecked(
+        &mut self,
+        deadline: Instant<Mono>,
+        task: Task,
+        index: u8,
+    ) -> Key {
+        self.0.push_unchecked(Ready {
+            deadline,
+            task,
+            index,
+        })
+    }
+
+    /// Dequeue the task with the earliest deadline
+    #[inline]
+    pub fn dequeue(&mut self) -> Option<(Task, u8)> {
+        self.0.pop().ok().map(|r| (r.task, r.index))
+    }
+
+    /// Calls `f` on the deadline of every queued instance
+    ///
+    /// # Safety
+    ///
+    /// `f` must not change the relative order of the deadlines, e.g. it must shift all of them by
+    /// the same amount
+    pub unsafe fn rebase<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Instant<Mono>),
+    {
+        self.0.for_each_mut(|r| f(&mut r.deadline))
+    }
+
+    /// Cancel the task identified by `key`
+    ///
+    /// Complexity is O(1).
+    #[inline]
+    pub fn cancel(&mut self, key: Key) -> Option<(Task, u8)> {
+        self.0.remove(key).map(|r| (r.task, r.index))
+    }
+}
+
+pub struct Ready<Mono, Task>
+where
+    Task: Copy,
+    Mono: Monotonic,
+{
+    pub deadline: Instant<Mono>,
+    pub task: Task,
+    pub index: u8,
+}
+
+impl<Mono, Task> Eq for Ready<Mono, Task>
+where
+    Task: Copy,
+    Mono: Monotonic,
+{
+}
+
+impl<Mono, Task> Ord for Ready<Mono, Task>
+where
+    Task: Copy,
+    Mono: Monotonic,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline.cmp(&other.deadline)
+    }
+}
+
+impl<Mono, Task> PartialEq for Ready<Mono, Task>
+where
+    Task: Copy,
+    Mono: Monotonic,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl<Mono, Task> PartialOrd for Ready<Mono, Task>
+where
+    Task: Copy,
+    Mono: Monotonic,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,250 @@
+//! Executor of the `async` software tasks
+//!
+//! Every instance of an `async` task owns a `FutureSlot` in a `static` array. The dispatcher of the
+//! task's priority level moves the future into the slot when it dispatches the instance and polls
+//! it again every time its waker is woken. Waking pends the dispatcher interrupt, so futures are
+//! only ever polled from their own priority level.
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    future::Future,
+    marker::PhantomPinned,
+    mem::{self, MaybeUninit},
+    pin::Pin,
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{export::interrupt, time::Instant, tq::Key, Monotonic};
+
+/// The part of a slot that its wakers point to
+struct Header {
+    woken: AtomicBool,
+    pend: fn(),
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, noop);
+
+unsafe fn clone(header: *const ()) -> RawWaker {
+    RawWaker::new(header, &VTABLE)
+}
+
+unsafe fn wake(header: *const ()) {
+    let header = &*(header as *const Header);
+
+    header.woken.store(true, Ordering::Release);
+    (header.pend)();
+}
+
+unsafe fn noop(_: *const ()) {}
+
+/// Type erased `poll` of the future stored in a slot
+type PollFn = unsafe fn(*mut u8, &mut Context<'_>) -> Poll<()>;
+
+unsafe fn poll<F>(future: *mut u8, cx: &mut Context<'_>) -> Poll<()>
+where
+    F: Future<Output = ()>,
+{
+    let future = future as *mut F;
+    let poll = Pin::new_unchecked(&mut *future).poll(cx);
+
+    if poll.is_ready() {
+        ptr::drop_in_place(future);
+    }
+
+    poll
+}
+
+#[repr(C, align(8))]
+struct Storage<const N: usize>([MaybeUninit<u8>; N]);
+
+/// Storage for the future of one instance of an `async` task
+///
+/// `N` is the size of the future, see `size_of_future`.
+pub struct FutureSlot<const N: usize> {
+    header: Header,
+    poll: Cell<Option<PollFn>>,
+    storage: UnsafeCell<Storage<N>>,
+}
+
+impl<const N: usize> FutureSlot<N> {
+    /// An empty slot whose wakers call `pend`
+    pub const fn new(pend: fn()) -> Self {
+        FutureSlot {
+            header: Header {
+                woken: AtomicBool::new(false),
+                pend,
+            },
+            poll: Cell::new(None),
+            storage: UnsafeCell::new(Storage([MaybeUninit::uninit(); N])),
+        }
+    }
+
+    /// Moves `future` into the slot and marks it as woken, so that the next
+    /// `poll_if_woken` polls it
+    ///
+    /// # Safety
+    ///
+    /// The slot must be empty and only be accessed from the priority level of its dispatcher
+    pub unsafe fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()>,
+    {
+        assert!(
+            mem::size_of::<F>() <= N && mem::align_of::<F>() <= mem::align_of::<Storage<N>>(),
+            "future does not fit its slot"
+        );
+
+        ptr::write(self.storage.get() as *mut F, future);
+        self.poll.set(Some(poll::<F>));
+        self.header.woken.store(true, Ordering::Release);
+    }
+
+    /// Whether the slot holds a future whose waker has been woken since the last poll
+    pub fn is_woken(&self) -> bool {
+        self.poll.get().is_some() && self.header.woken.load(Ordering::Acquire)
+    }
+
+    /// Polls the future in the slot if its waker has been woken since the last poll
+    ///
+    /// Returns `true` when the future completes; the slot is empty again from then on.
+    ///
+    /// # Safety
+    ///
+    /// The slot must only be accessed from the priority level of its dispatcher, and must not move
+    /// once a future has been spawned into it
+    pub unsafe fn poll_if_woken(&self) -> bool {
+        let poll = match self.poll.get() {
+            Some(poll) if self.header.woken.load(Ordering::Acquire) => poll,
+            _ => return false,
+        };
+
+        // A wake that happens from now on is seen by the poll below
+        self.header.woken.store(false, Ordering::Release);
+
+        let waker = Waker::from_raw(RawWaker::new(
+            &self.header as *const Header as *const (),
+            &VTABLE,
+        ));
+        let mut cx = Context::from_waker(&waker);
+
+        if poll(self.storage.get() as *mut u8, &mut cx).is_ready() {
+            self.poll.set(None);
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The size of the future `task` returns
+///
+/// `task` adapts an `async` task to a context and a message (tuple) argument.
+pub const fn size_of_future<C, I, Fut, F>(_task: &F) -> usize
+where
+    F: FnOnce(C, I) -> Fut,
+{
+    mem::size_of::<Fut>()
+}
+
+/// The timer queue operations a `Delay` needs, generated for each monotonic
+pub struct DelayOps<Mono>
+where
+    Mono: Monotonic,
+{
+    /// Reads the monotonic
+    pub now: fn() -> Instant<Mono>,
+    /// Enqueues an entry that wakes the waker behind the pointer at `instant`; `None` if the
+    /// timer queue is full, which the sizing of the queue rules out for one delay per instance
+    pub enqueue: unsafe fn(Instant<Mono>, *mut Option<Waker>) -> Option<Key>,
+    /// Removes an entry from the timer queue, if it's still there
+    pub cancel: fn(Key),
+}
+
+/// A future that completes once a monotonic has reached an instant
+///
+/// Returned by `monotonics::<Mono>::delay_until` and `monotonics::<Mono>::delay`. The delay is
+/// entered into the timer queue of the monotonic when it's first polled and taken out again when
+/// it's dropped. The timer queue has room for one pending delay per instance of an `async` task;
+/// a delay that finds the queue full, e.g. because more delays are joined, wakes its task to
+/// retry, which keeps the dispatcher busy until the timer queue handler takes an entry out.
+pub struct Delay<Mono>
+where
+    Mono: Monotonic + 'static,
+{
+    instant: Instant<Mono>,
+    ops: &'static DelayOps<Mono>,
+    key: Option<Key>,
+    // The timer queue entry points here, so the delay must not move once it has been polled
+    waker: Option<Waker>,
+    _pinned: PhantomPinned,
+}
+
+impl<Mono> Delay<Mono>
+where
+    Mono: Monotonic + 'static,
+{
+    #[doc(hidden)]
+    pub fn new(instant: Instant<Mono>, ops: &'static DelayOps<Mono>) -> Self {
+        Delay {
+            instant,
+            ops,
+            key: None,
+            waker: None,
+            _pinned: PhantomPinned,
+        }
+    }
+
+    /// The instant this delay completes at
+    pub fn instant(&self) -> Instant<Mono> {
+        self.instant
+    }
+}
+
+impl<Mono> Future for Delay<Mono>
+where
+    Mono: Monotonic + 'static,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // Safety: nothing is moved out of the delay
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if (this.ops.now)() >= this.instant {
+            if let Some(key) = this.key.take() {
+                (this.ops.cancel)(key);
+            }
+
+            return Poll::Ready(());
+        }
+
+        // The timer queue handler takes the waker in a critical section
+        interrupt::free(|_| this.waker = Some(cx.waker().clone()));
+
+        if this.key.is_none() {
+            // Safety: the delay is pinned and removes the entry before it's dropped
+            this.key = unsafe { (this.ops.enqueue)(this.instant, &mut this.waker) };
+
+            if this.key.is_none() {
+                // The timer queue is full; poll again until an entry has been taken out
+                cx.waker().wake_by_ref();
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<Mono> Drop for Delay<Mono>
+where
+    Mono: Monotonic + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            (self.ops.cancel)(key);
+        }
+    }
+}
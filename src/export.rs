@@ -3,25 +3,44 @@ use core::{
     sync::atomic::{AtomicBool, Ordering},
 };
 
-pub use crate::tq::{NotReady, TimerQueue};
+pub use crate::edf::EdfQueue;
+pub use crate::executor::{size_of_future, DelayOps, FutureSlot};
+#[cfg(feature = "host")]
+pub use crate::host::{
+    basepri, cycle_count, interrupt, pend_svcall, register_exception, register_interrupt, wfi,
+    Peripherals, SystemHandler, NVIC, SCB, SYST,
+};
+pub use crate::linked_list::Key as ReadyKey;
+#[cfg(feature = "lock-trace")]
+pub use crate::lock_trace::{register as register_locks, Counter as LockCounter};
+pub use crate::multiplex::Multiplexer;
+pub use crate::pool::{Node as PoolNode, Pool};
+pub use crate::rq::ReadyQueue;
+#[cfg(feature = "stats")]
+pub use crate::stats::{register as register_stats, Counter as StatsCounter};
+pub use crate::tq::{Key, Node, NotReady, TimerQueue};
 pub use bare_metal::CriticalSection;
-#[cfg(armv7m)]
+pub use cortex_m::peripheral::{syst::SystClkSource, DWT};
+#[cfg(all(armv7m, not(feature = "host")))]
 pub use cortex_m::register::basepri;
+#[cfg(not(feature = "host"))]
 pub use cortex_m::{
     asm::wfi,
     interrupt,
-    peripheral::{scb::SystemHandler, syst::SystClkSource, DWT, NVIC},
+    peripheral::{scb::SystemHandler, NVIC, SCB, SYST},
     Peripherals,
 };
+pub use cortex_m_rtic_macros::auto_dispatchers;
+pub use generic_array::ArrayLength;
 use heapless::spsc::SingleCore;
 pub use heapless::{consts, i::Queue as iQueue, spsc::Queue};
 pub use heapless::{i::BinaryHeap as iBinaryHeap, BinaryHeap};
 pub use rtic_monotonic as monotonic;
 
 pub type SCFQ<N> = Queue<u8, N, u8, SingleCore>;
-pub type SCRQ<T, N> = Queue<(T, u8), N, u8, SingleCore>;
+pub type ChannelQueue<T, N> = Queue<T, N, u8, SingleCore>;
 
-#[cfg(armv7m)]
+#[cfg(any(armv7m, feature = "host"))]
 #[inline(always)]
 pub fn run<F>(priority: u8, f: F)
 where
@@ -38,7 +57,7 @@ where
     }
 }
 
-#[cfg(not(armv7m))]
+#[cfg(not(any(armv7m, feature = "host")))]
 #[inline(always)]
 pub fn run<F>(_priority: u8, f: F)
 where
@@ -79,7 +98,7 @@ impl Priority {
     ///
     /// Will overwrite the current Priority
     #[inline(always)]
-    pub unsafe fn new(value: u8) -> Self {
+    pub const unsafe fn new(value: u8) -> Self {
         Priority {
             inner: Cell::new(value),
         }
@@ -127,7 +146,7 @@ where
 ///
 /// Writing to the BASEPRI
 /// Dereferencing a raw pointer
-#[cfg(armv7m)]
+#[cfg(any(armv7m, feature = "host"))]
 #[inline(always)]
 pub unsafe fn lock<T, R>(
     ptr: *mut T,
@@ -164,7 +183,7 @@ pub unsafe fn lock<T, R>(
 ///
 /// Writing to the PRIMASK
 /// Dereferencing a raw pointer
-#[cfg(not(armv7m))]
+#[cfg(not(any(armv7m, feature = "host")))]
 #[inline(always)]
 pub unsafe fn lock<T, R>(
     ptr: *mut T,
@@ -185,6 +204,74 @@ pub unsafe fn lock<T, R>(
     }
 }
 
+/// Reads the DWT cycle counter, which the application must have enabled
+#[cfg(all(not(armv6m), not(feature = "host")))]
+#[inline(always)]
+pub fn cycle_count() -> u32 {
+    unsafe { (*DWT::ptr()).cyccnt.read() }
+}
+
+/// Reads the cycle counter that measures `budget_cycles`
+#[cfg(not(armv6m))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rtic_internal_budget_cycles {
+    () => {
+        $crate::export::cycle_count()
+    };
+}
+
+// ARMv6-M has no cycle counter; fail with a diagnostic rather than an unresolved `cycle_count`
+#[cfg(armv6m)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rtic_internal_budget_cycles {
+    () => {
+        compile_error!(
+            "`budget_cycles` needs the DWT cycle counter, which ARMv6-M doesn't have; \
+             use `budget` with a monotonic instead"
+        )
+    };
+}
+
+pub use crate::__rtic_internal_budget_cycles as budget_cycles;
+
+/// Sets the SVCall exception as pending, for apps that dispatch tasks from it
+///
+/// The pending bit is only accessible from software on ARMv7-M and up.
+#[cfg(all(not(armv6m), not(feature = "host")))]
+#[inline(always)]
+pub fn pend_svcall() {
+    // SVCALLPENDED
+    unsafe { (*SCB::ptr()).shcsr.modify(|r| r | 1 << 15) }
+}
+
+/// Pends the SVCall dispatcher
+#[cfg(not(armv6m))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rtic_internal_pend_svcall {
+    () => {
+        $crate::export::pend_svcall()
+    };
+}
+
+// ARMv6-M can't pend SVCall from software; fail with a diagnostic rather than an unresolved
+// `pend_svcall`
+#[cfg(armv6m)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __rtic_internal_pend_svcall {
+    () => {
+        compile_error!(
+            "`SVCall` can't be pended from software on ARMv6-M, so it can't be a dispatcher; \
+             use `PendSV` or a device interrupt instead"
+        )
+    };
+}
+
+pub use crate::__rtic_internal_pend_svcall as pend_svcall;
+
 #[inline]
 pub fn logical2hw(logical: u8, nvic_prio_bits: u8) -> u8 {
     ((1 << nvic_prio_bits) - logical) << (8 - nvic_prio_bits)
@@ -0,0 +1,551 @@
+//! Host (x86_64 Linux) execution backend
+//!
+//! This module emulates, in software, the parts of the Cortex-M core that the code generated by
+//! `#[rtic::app]` relies on: the NVIC (pending bits, enable bits and priorities), the configurable
+//! system handlers, BASEPRI and PRIMASK. Pending an interrupt that is allowed to preempt the
+//! current context runs its handler right away, on the caller's stack, which mirrors how the
+//! hardware nests exception handlers.
+//!
+//! Enable the `host` feature to select this backend. The application is then started with the
+//! generated `app::run` function, which returns once the application has nothing left to do, that
+//! is when `idle` (or the default `wfi` loop) waits for an interrupt and none is pending, or when
+//! [`exit`] is called.
+//!
+//! The emulated core is thread local, so each test thread sees its own NVIC. The `static`
+//! variables generated by `#[rtic::app]` are not, so an application must only be run once per
+//! process and not from several threads at the same time.
+
+use core::{cell::RefCell, marker::PhantomData};
+use std::panic::{self, AssertUnwindSafe};
+
+use bare_metal::CriticalSection;
+use cortex_m::interrupt::InterruptNumber;
+
+/// Number of exception slots that precede the device interrupts in the vector table
+const EXCEPTIONS: usize = 16;
+
+/// Total number of emulated vectors
+const VECTORS: usize = EXCEPTIONS + 240;
+
+/// Exception number of SVCall
+const SVCALL: usize = 11;
+
+/// Exception number of PendSV
+const PENDSV: usize = 14;
+
+/// Exception number of SysTick
+const SYSTICK: usize = 15;
+
+#[derive(Clone, Copy)]
+struct Vector {
+    handler: Option<unsafe fn()>,
+    priority: u8,
+    enabled: bool,
+    pending: bool,
+}
+
+struct Core {
+    vectors: Vec<Vector>,
+    basepri: u8,
+    primask: bool,
+    scr: u32,
+    cyccnt: u32,
+    // Vectors of the handlers that are currently executing, innermost last
+    active: Vec<usize>,
+}
+
+impl Core {
+    fn new() -> Self {
+        let mut vectors = vec![
+            Vector {
+                handler: None,
+                priority: 0,
+                enabled: false,
+                pending: false,
+            };
+            VECTORS
+        ];
+
+        // These exceptions can not be disabled
+        vectors[SVCALL].enabled = true;
+        vectors[PENDSV].enabled = true;
+
+        Core {
+            vectors,
+            basepri: 0,
+            primask: false,
+            scr: 0,
+            cyccnt: 0,
+            active: Vec::new(),
+        }
+    }
+
+    /// Hardware priority a pending vector must be below (i.e. more urgent than) to be taken
+    ///
+    /// Like on the hardware, this follows the current priorities of the active handlers, which may
+    /// have changed since they were entered.
+    fn threshold(&self) -> u16 {
+        let running = self
+            .active
+            .iter()
+            .map(|&index| u16::from(self.vectors[index].priority))
+            .min()
+            .unwrap_or(256);
+
+        if self.basepri != 0 {
+            running.min(u16::from(self.basepri))
+        } else {
+            running
+        }
+    }
+
+    /// Marks the most urgent runnable vector as active and returns its handler
+    fn take_next(&mut self) -> Option<unsafe fn()> {
+        if self.primask {
+            return None;
+        }
+
+        let threshold = self.threshold();
+        let (index, _) = self
+            .vectors
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.pending && v.enabled && u16::from(v.priority) < threshold)
+            .min_by_key(|(index, v)| (v.priority, *index))?;
+
+        let vector = &mut self.vectors[index];
+        vector.pending = false;
+        self.active.push(index);
+
+        Some(
+            vector
+                .handler
+                .unwrap_or_else(|| panic!("no handler has been registered for vector {}", index)),
+        )
+    }
+
+    fn is_quiescent(&self) -> bool {
+        !self.vectors.iter().any(|v| v.pending && v.enabled)
+    }
+}
+
+std::thread_local! {
+    static CORE: RefCell<Core> = RefCell::new(Core::new());
+}
+
+fn with<R>(f: impl FnOnce(&mut Core) -> R) -> R {
+    CORE.with(|core| f(&mut core.borrow_mut()))
+}
+
+/// Runs every pending handler that is allowed to preempt the current context
+fn dispatch() {
+    while let Some(handler) = with(Core::take_next) {
+        unsafe { handler() };
+
+        with(|core| core.active.pop());
+    }
+}
+
+fn irq_index<I>(interrupt: I) -> usize
+where
+    I: InterruptNumber,
+{
+    EXCEPTIONS + usize::from(interrupt.number())
+}
+
+/// Marker payload used to unwind out of the application
+struct Exit;
+
+/// Stops the running application and returns control to the caller of `app::run`
+pub fn exit() -> ! {
+    panic::resume_unwind(std::boxed::Box::new(Exit))
+}
+
+/// Runs `main` (the generated entry point) until the application exits
+///
+/// The emulated core is reset before and after the run.
+pub fn run(main: unsafe fn() -> !) {
+    with(|core| *core = Core::new());
+
+    let result: Result<(), _> = panic::catch_unwind(AssertUnwindSafe(|| unsafe { main() }));
+
+    with(|core| *core = Core::new());
+
+    if let Err(payload) = result {
+        if !payload.is::<Exit>() {
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+/// Registers the handler that services the device `interrupt`
+///
+/// # Safety
+///
+/// The handler will be called whenever the interrupt is pended and unmasked
+pub unsafe fn register_interrupt<I>(interrupt: I, handler: unsafe fn())
+where
+    I: InterruptNumber,
+{
+    with(|core| core.vectors[irq_index(interrupt)].handler = Some(handler))
+}
+
+/// Registers the handler that services the system `exception`
+///
+/// # Safety
+///
+/// The handler will be called whenever the exception is pended and enabled
+pub unsafe fn register_exception(exception: SystemHandler, handler: unsafe fn()) {
+    with(|core| core.vectors[exception.index()].handler = Some(handler))
+}
+
+/// Emulated version of `cortex_m::asm::wfi`
+///
+/// Services all pending interrupts. If no enabled interrupt is pending afterwards nothing can ever
+/// wake the core up again, so the application [`exit`]s.
+pub fn wfi() {
+    dispatch();
+
+    if with(|core| core.is_quiescent()) {
+        exit()
+    }
+}
+
+/// Emulated DWT cycle counter
+///
+/// It doesn't count on its own; tests move it forward with [`advance_cycles`].
+pub fn cycle_count() -> u32 {
+    with(|core| core.cyccnt)
+}
+
+/// Moves the emulated cycle counter forward by `cycles`, wrapping like the hardware counter
+pub fn advance_cycles(cycles: u32) {
+    with(|core| core.cyccnt = core.cyccnt.wrapping_add(cycles))
+}
+
+/// Sets the SVCall exception as pending, like writing `SVCALLPENDED` in the SHCSR does
+pub fn pend_svcall() {
+    with(|core| core.vectors[SVCALL].pending = true);
+
+    dispatch()
+}
+
+/// Emulated version of `cortex_m::interrupt`
+pub mod interrupt {
+    use super::{dispatch, with, CriticalSection};
+
+    /// Disables all interrupts
+    #[inline]
+    pub fn disable() {
+        with(|core| core.primask = true)
+    }
+
+    /// Enables all interrupts
+    ///
+    /// # Safety
+    ///
+    /// - Do not call this function inside an `interrupt::free` critical section
+    #[inline]
+    pub unsafe fn enable() {
+        with(|core| core.primask = false);
+
+        dispatch()
+    }
+
+    /// Executes the closure `f` in an interrupt-free context
+    pub fn free<F, R>(f: F) -> R
+    where
+        F: FnOnce(&CriticalSection) -> R,
+    {
+        let primask = with(|core| core::mem::replace(&mut core.primask, true));
+
+        let r = f(unsafe { &CriticalSection::new() });
+
+        if !primask {
+            unsafe { enable() }
+        }
+
+        r
+    }
+}
+
+/// Emulated version of `cortex_m::register::basepri`
+pub mod basepri {
+    use super::{dispatch, with};
+
+    /// Reads the BASEPRI register
+    #[inline]
+    pub fn read() -> u8 {
+        with(|core| core.basepri)
+    }
+
+    /// Writes to the BASEPRI register
+    ///
+    /// # Safety
+    ///
+    /// Lowering BASEPRI can break memory safety if it is done inside a critical section
+    #[inline]
+    pub unsafe fn write(basepri: u8) {
+        let lowered = with(|core| {
+            let lowered = basepri == 0 || (core.basepri != 0 && basepri > core.basepri);
+            core.basepri = basepri;
+            lowered
+        });
+
+        if lowered {
+            dispatch()
+        }
+    }
+}
+
+/// System handlers that have a configurable priority
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemHandler {
+    MemoryManagement,
+    BusFault,
+    UsageFault,
+    SecureFault,
+    SVCall,
+    DebugMonitor,
+    PendSV,
+    SysTick,
+}
+
+impl SystemHandler {
+    fn index(self) -> usize {
+        match self {
+            SystemHandler::MemoryManagement => 4,
+            SystemHandler::BusFault => 5,
+            SystemHandler::UsageFault => 6,
+            SystemHandler::SecureFault => 7,
+            SystemHandler::SVCall => SVCALL,
+            SystemHandler::DebugMonitor => 12,
+            SystemHandler::PendSV => PENDSV,
+            SystemHandler::SysTick => SYSTICK,
+        }
+    }
+}
+
+/// Emulated Nested Vector Interrupt Controller
+#[allow(clippy::upper_case_acronyms)]
+pub struct NVIC {
+    _marker: PhantomData<*const ()>,
+}
+
+impl NVIC {
+    /// Disables `interrupt`
+    #[inline]
+    pub fn mask<I>(interrupt: I)
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].enabled = false)
+    }
+
+    /// Enables `interrupt`
+    ///
+    /// # Safety
+    ///
+    /// Unmasking an interrupt can break critical sections that rely on masking it
+    #[inline]
+    pub unsafe fn unmask<I>(interrupt: I)
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].enabled = true);
+
+        dispatch()
+    }
+
+    /// Forces `interrupt` into the pending state
+    #[inline]
+    pub fn pend<I>(interrupt: I)
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].pending = true);
+
+        dispatch()
+    }
+
+    /// Clears `interrupt`'s pending state
+    #[inline]
+    pub fn unpend<I>(interrupt: I)
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].pending = false)
+    }
+
+    /// Checks if `interrupt` is pending
+    #[inline]
+    pub fn is_pending<I>(interrupt: I) -> bool
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].pending)
+    }
+
+    /// Checks if `interrupt` is enabled
+    #[inline]
+    pub fn is_enabled<I>(interrupt: I) -> bool
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].enabled)
+    }
+
+    /// Returns the hardware priority of `interrupt`
+    #[inline]
+    pub fn get_priority<I>(interrupt: I) -> u8
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].priority)
+    }
+
+    /// Sets the hardware priority of `interrupt` to `prio`
+    ///
+    /// # Safety
+    ///
+    /// Changing priority levels can break priority-based critical sections
+    #[inline]
+    pub unsafe fn set_priority<I>(&mut self, interrupt: I, prio: u8)
+    where
+        I: InterruptNumber,
+    {
+        with(|core| core.vectors[irq_index(interrupt)].priority = prio);
+
+        // A lower priority may let pending handlers preempt the active one
+        dispatch()
+    }
+}
+
+/// Emulated System Control Register
+pub struct Scr {
+    _marker: PhantomData<*const ()>,
+}
+
+impl Scr {
+    /// Reads the register
+    #[inline]
+    pub fn read(&self) -> u32 {
+        with(|core| core.scr)
+    }
+
+    /// Read-modify-writes the register
+    ///
+    /// # Safety
+    ///
+    /// Kept `unsafe` to match the hardware register API
+    #[inline]
+    pub unsafe fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(u32) -> u32,
+    {
+        with(|core| core.scr = f(core.scr))
+    }
+}
+
+/// Emulated System Control Block
+#[allow(clippy::upper_case_acronyms)]
+pub struct SCB {
+    /// System Control Register
+    pub scr: Scr,
+}
+
+impl SCB {
+    /// Sets the PendSV exception as pending
+    #[inline]
+    pub fn set_pendsv() {
+        with(|core| core.vectors[PENDSV].pending = true);
+
+        dispatch()
+    }
+
+    /// Sets the SysTick exception as pending
+    #[inline]
+    pub fn set_pendst() {
+        with(|core| core.vectors[SYSTICK].pending = true);
+
+        dispatch()
+    }
+
+    /// Returns the hardware priority of the system `handler`
+    #[inline]
+    pub fn get_priority(handler: SystemHandler) -> u8 {
+        with(|core| core.vectors[handler.index()].priority)
+    }
+
+    /// Sets the hardware priority of the system `handler` to `prio`
+    ///
+    /// # Safety
+    ///
+    /// Changing priority levels can break priority-based critical sections
+    #[inline]
+    pub unsafe fn set_priority(&mut self, handler: SystemHandler, prio: u8) {
+        with(|core| core.vectors[handler.index()].priority = prio);
+
+        dispatch()
+    }
+}
+
+/// Emulated SysTick timer
+///
+/// Only the interrupt enable bit is modelled; time is provided by the application's monotonic.
+#[allow(clippy::upper_case_acronyms)]
+pub struct SYST {
+    _0: (),
+}
+
+impl SYST {
+    /// Enables the SysTick interrupt
+    #[inline]
+    pub fn enable_interrupt(&mut self) {
+        with(|core| core.vectors[SYSTICK].enabled = true);
+
+        dispatch()
+    }
+
+    /// Disables the SysTick interrupt
+    #[inline]
+    pub fn disable_interrupt(&mut self) {
+        with(|core| core.vectors[SYSTICK].enabled = false)
+    }
+}
+
+/// Emulated core peripherals
+#[allow(non_snake_case)]
+pub struct Peripherals {
+    /// Nested Vector Interrupt Controller
+    pub NVIC: NVIC,
+
+    /// System Control Block
+    pub SCB: SCB,
+
+    /// SysTick: System Timer
+    pub SYST: SYST,
+}
+
+impl Peripherals {
+    /// Returns all the core peripherals *without* checking if they have already been taken
+    ///
+    /// # Safety
+    ///
+    /// The emulated peripherals are shared through the thread-local core state
+    #[inline]
+    pub unsafe fn steal() -> Self {
+        Peripherals {
+            NVIC: NVIC {
+                _marker: PhantomData,
+            },
+            SCB: SCB {
+                scr: Scr {
+                    _marker: PhantomData,
+                },
+            },
+            SYST: SYST { _0: () },
+        }
+    }
+}
@@ -0,0 +1,601 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+pub use generic_array::ArrayLength;
+use generic_array::GenericArray;
+
+use crate::linked_list::kind;
+
+/// Marks an empty free list.
+const NONE: u16 = u16::MAX;
+
+/// A node of the heap.
+///
+/// Node `i` doubles as entry `i` of the heap array: `heap` is the node that currently sits at
+/// position `i` of the heap, while `pos` is the heap position of the element stored in this node
+/// (or the next free node while the node is unused).
+pub struct Node<T> {
+    val: MaybeUninit<T>,
+    pos: u16,
+    heap: u16,
+    generation: u32,
+    // When the element was pushed or updated, to keep equal elements in that order
+    seq: u32,
+}
+
+/// Identifies an element for as long as it stays in the heap.
+///
+/// The generation of a node is bumped every time the node is released, so a key to an element
+/// that has been popped or removed never matches a newer element stored in the same node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    index: u16,
+    generation: u32,
+}
+
+/// A binary heap that supports removing arbitrary elements by key.
+///
+/// Unlike the sorted [`LinkedList`](crate::linked_list::LinkedList), whose insertion walks the
+/// list, every operation here has a worst-case complexity of O(log N). Like the list, equal
+/// elements come out in the order they were pushed (or last updated).
+pub struct IndexedHeap<T, Kind, N>
+where
+    T: PartialEq + PartialOrd,
+    Kind: kind::Kind,
+    N: ArrayLength<Node<T>>,
+{
+    nodes: MaybeUninit<GenericArray<Node<T>, N>>,
+    len: u16,
+    free: u16,
+    seq: u32,
+    _kind: PhantomData<Kind>,
+}
+
+impl<T, Kind, N> IndexedHeap<T, Kind, N>
+where
+    T: PartialEq + PartialOrd,
+    Kind: kind::Kind,
+    N: ArrayLength<Node<T>>,
+{
+    /// Internal helper to not do pointer arithmetic all over the place.
+    #[inline]
+    fn node_at(&self, index: u16) -> &Node<T> {
+        // Safety: The entire `self.nodes` is initialized in `new`, which makes this safe.
+        unsafe { &*(self.nodes.as_ptr() as *const Node<T>).add(index as usize) }
+    }
+
+    /// Internal helper to not do pointer arithmetic all over the place.
+    #[inline]
+    fn node_at_mut(&mut self, index: u16) -> &mut Node<T> {
+        // Safety: The entire `self.nodes` is initialized in `new`, which makes this safe.
+        unsafe { &mut *(self.nodes.as_mut_ptr() as *mut Node<T>).add(index as usize) }
+    }
+
+    /// Internal helper to get the element at heap position `pos`.
+    #[inline]
+    fn data_at(&self, pos: u16) -> &T {
+        unsafe { &*self.node_at(self.node_at(pos).heap).val.as_ptr() }
+    }
+
+    /// Internal helper: whether the element at heap position `a` goes before the one at `b`.
+    #[inline]
+    fn before(&self, a: u16, b: u16) -> bool {
+        match self.data_at(a).partial_cmp(self.data_at(b)) {
+            // The older one first; the difference stays meaningful across a wrap of the counter
+            Some(Ordering::Equal) => {
+                let (a, b) = (self.node_at(a).heap, self.node_at(b).heap);
+
+                (self.node_at(a).seq.wrapping_sub(self.node_at(b).seq) as i32) < 0
+            }
+            ordering => ordering == Kind::ordering(),
+        }
+    }
+
+    /// Internal helper: marks node `index` as the newest of the elements that compare equal.
+    #[inline]
+    fn stamp(&mut self, index: u16) {
+        let seq = self.seq;
+        self.seq = seq.wrapping_add(1);
+        self.node_at_mut(index).seq = seq;
+    }
+
+    /// Internal helper: places node `index` at heap position `pos`.
+    #[inline]
+    fn place(&mut self, pos: u16, index: u16) {
+        self.node_at_mut(pos).heap = index;
+        self.node_at_mut(index).pos = pos;
+    }
+
+    /// Internal helper: swaps the elements at heap positions `a` and `b`.
+    #[inline]
+    fn swap(&mut self, a: u16, b: u16) {
+        let (ia, ib) = (self.node_at(a).heap, self.node_at(b).heap);
+        self.place(a, ib);
+        self.place(b, ia);
+    }
+
+    /// Internal helper: moves the element at heap position `pos` towards the root.
+    fn sift_up(&mut self, mut pos: u16) {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+
+            if !self.before(pos, parent) {
+                break;
+            }
+
+            self.swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    /// Internal helper: moves the element at heap position `pos` towards the leaves.
+    fn sift_down(&mut self, mut pos: u16) {
+        loop {
+            let left = 2 * pos as u32 + 1;
+            if left >= self.len as u32 {
+                break;
+            }
+
+            let left = left as u16;
+            let right = left + 1;
+            let child = if right < self.len && self.before(right, left) {
+                right
+            } else {
+                left
+            };
+
+            if !self.before(child, pos) {
+                break;
+            }
+
+            self.swap(pos, child);
+            pos = child;
+        }
+    }
+
+    /// Create a new heap.
+    pub fn new() -> Self {
+        let mut heap = IndexedHeap {
+            nodes: MaybeUninit::uninit(),
+            len: 0,
+            free: if N::U16 == 0 { NONE } else { 0 },
+            seq: 0,
+            _kind: PhantomData,
+        };
+
+        let len = N::U16;
+
+        // Initialize indexes, chaining all nodes into the free list
+        for index in 0..len {
+            let next = if index + 1 < len { index + 1 } else { NONE };
+
+            unsafe {
+                (heap.nodes.as_mut_ptr() as *mut Node<T>)
+                    .add(index as usize)
+                    .write(Node {
+                        val: MaybeUninit::uninit(),
+                        pos: next,
+                        heap: 0,
+                        generation: 0,
+                        seq: 0,
+                    });
+            }
+        }
+
+        heap
+    }
+
+    /// Push unchecked
+    ///
+    /// Complexity is O(log N).
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the heap is not full.
+    pub unsafe fn push_unchecked(&mut self, value: T) -> Key {
+        let index = self.free;
+        self.free = self.node_at(index).pos;
+        self.node_at_mut(index).val.as_mut_ptr().write(value);
+        self.stamp(index);
+
+        let pos = self.len;
+        self.len += 1;
+        self.place(pos, index);
+        self.sift_up(pos);
+
+        Key {
+            index,
+            generation: self.node_at(index).generation,
+        }
+    }
+
+    /// Pushes an element to the heap.
+    ///
+    /// Complexity is O(log N).
+    pub fn push(&mut self, value: T) -> Result<Key, T> {
+        if !self.is_full() {
+            Ok(unsafe { self.push_unchecked(value) })
+        } else {
+            Err(value)
+        }
+    }
+
+    /// Get the element identified by `key`, if it's still in the heap.
+    ///
+    /// Complexity is O(1).
+    pub fn get(&self, key: Key) -> Option<&T> {
+        if self.is_live(key) {
+            Some(unsafe { &*self.node_at(key.index).val.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Remove the element identified by `key`, if it's still in the heap.
+    ///
+    /// Complexity is O(log N).
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if self.is_live(key) {
+            // Safety: a matching generation means the node has not been released since `key` was
+            // handed out, so it's still in the heap
+            Some(unsafe { self.remove_unchecked(key.index) })
+        } else {
+            None
+        }
+    }
+
+    /// Change the element identified by `key` with `f` and move it to its new place.
+    ///
+    /// The key stays valid. Returns `false` if `key` no longer refers to an element.
+    ///
+    /// Complexity is O(log N).
+    pub fn update<F>(&mut self, key: Key, f: F) -> bool
+    where
+        F: FnOnce(&mut T),
+    {
+        if !self.is_live(key) {
+            return false;
+        }
+
+        f(unsafe { &mut *self.node_at_mut(key.index).val.as_mut_ptr() });
+        self.stamp(key.index);
+
+        let pos = self.node_at(key.index).pos;
+        if pos > 0 && self.before(pos, (pos - 1) / 2) {
+            self.sift_up(pos);
+        } else {
+            self.sift_down(pos);
+        }
+
+        true
+    }
+
+    /// Internal helper: checks that `key` still refers to an element of the heap.
+    #[inline]
+    fn is_live(&self, key: Key) -> bool {
+        key.index < N::U16 && self.node_at(key.index).generation == key.generation
+    }
+
+    /// Internal helper: removes the element stored in node `index` and releases the node.
+    ///
+    /// # Safety
+    ///
+    /// `index` must hold an element of the heap.
+    unsafe fn remove_unchecked(&mut self, index: u16) -> T {
+        let pos = self.node_at(index).pos;
+        let last = self.len - 1;
+
+        // Fill the hole with the last element and restore the heap property around it
+        self.len = last;
+        if pos != last {
+            let moved = self.node_at(last).heap;
+            self.place(pos, moved);
+
+            if pos > 0 && self.before(pos, (pos - 1) / 2) {
+                self.sift_up(pos);
+            } else {
+                self.sift_down(pos);
+            }
+        }
+
+        // Release the node into the free list and invalidate outstanding keys
+        let free = self.free;
+        let node = self.node_at_mut(index);
+        node.pos = free;
+        node.generation = node.generation.wrapping_add(1);
+        let val = node.val.as_ptr().read();
+        self.free = index;
+
+        val
+    }
+
+    /// Peek at the first element.
+    pub fn peek(&self) -> Option<&T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.data_at(0))
+        }
+    }
+
+    /// Get the key of the first element.
+    pub fn peek_key(&self) -> Option<Key> {
+        if self.is_empty() {
+            None
+        } else {
+            let index = self.node_at(0).heap;
+
+            Some(Key {
+                index,
+                generation: self.node_at(index).generation,
+            })
+        }
+    }
+
+    /// Pop unchecked
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the heap is not empty.
+    pub unsafe fn pop_unchecked(&mut self) -> T {
+        self.remove_unchecked(self.node_at(0).heap)
+    }
+
+    /// Pops the first element of the heap.
+    ///
+    /// Complexity is O(log N).
+    pub fn pop(&mut self) -> Result<T, ()> {
+        if !self.is_empty() {
+            Ok(unsafe { self.pop_unchecked() })
+        } else {
+            Err(())
+        }
+    }
+
+    /// Calls `f` on every element, in no particular order.
+    ///
+    /// Complexity is O(N).
+    ///
+    /// # Safety
+    ///
+    /// `f` must not change the relative order of the elements.
+    pub unsafe fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T),
+    {
+        for pos in 0..self.len {
+            let index = self.node_at(pos).heap;
+
+            f(&mut *self.node_at_mut(index).val.as_mut_ptr());
+        }
+    }
+
+    /// Checks if the heap is full.
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.free == NONE
+    }
+
+    /// Checks if the heap is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements in the heap.
+    #[inline]
+    pub fn len(&self) -> usize {
+        usize::from(self.len)
+    }
+}
+
+impl<T, Kind, N> Drop for IndexedHeap<T, Kind, N>
+where
+    T: PartialEq + PartialOrd,
+    Kind: kind::Kind,
+    N: ArrayLength<Node<T>>,
+{
+    fn drop(&mut self) {
+        for pos in 0..self.len {
+            let index = self.node_at(pos).heap;
+
+            unsafe {
+                ptr::drop_in_place(self.node_at_mut(index).val.as_mut_ptr());
+            }
+        }
+    }
+}
+
+impl<T, Kind, N> fmt::Debug for IndexedHeap<T, Kind, N>
+where
+    T: PartialEq + PartialOrd + core::fmt::Debug,
+    Kind: kind::Kind,
+    N: ArrayLength<Node<T>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len).map(|pos| self.data_at(pos)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linked_list::{Max, Min};
+    use generic_array::typenum::consts::*;
+
+    #[test]
+    fn test_peek() {
+        let mut heap: IndexedHeap<u32, Max, U3> = IndexedHeap::new();
+
+        heap.push(1).unwrap();
+        assert_eq!(heap.peek().unwrap(), &1);
+
+        heap.push(2).unwrap();
+        assert_eq!(heap.peek().unwrap(), &2);
+
+        heap.push(3).unwrap();
+        assert_eq!(heap.peek().unwrap(), &3);
+
+        let mut heap: IndexedHeap<u32, Min, U3> = IndexedHeap::new();
+
+        heap.push(2).unwrap();
+        heap.push(1).unwrap();
+        heap.push(3).unwrap();
+        assert_eq!(heap.peek().unwrap(), &1);
+    }
+
+    #[test]
+    fn test_full_and_empty() {
+        let mut heap: IndexedHeap<u32, Min, U3> = IndexedHeap::new();
+        assert!(heap.is_empty());
+
+        heap.push(1).unwrap();
+        heap.push(2).unwrap();
+        heap.push(3).unwrap();
+        assert!(heap.is_full());
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.push(4), Err(4));
+
+        assert_eq!(heap.pop(), Ok(1));
+        assert_eq!(heap.pop(), Ok(2));
+        assert_eq!(heap.pop(), Ok(3));
+        assert_eq!(heap.pop(), Err(()));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_zero_size() {
+        let mut heap: IndexedHeap<u32, Min, U0> = IndexedHeap::new();
+
+        assert!(heap.is_empty());
+        assert!(heap.is_full());
+        assert_eq!(heap.push(1), Err(1));
+    }
+
+    #[test]
+    fn test_sorted_pops() {
+        let mut heap: IndexedHeap<u32, Min, U16> = IndexedHeap::new();
+
+        for value in &[9, 3, 14, 0, 7, 7, 12, 1, 15, 5, 2, 11, 8, 4, 13, 6] {
+            heap.push(*value).unwrap();
+        }
+
+        let mut last = 0;
+        while let Ok(value) = heap.pop() {
+            assert!(last <= value);
+            last = value;
+        }
+    }
+
+    #[test]
+    fn test_remove_by_key() {
+        let mut heap: IndexedHeap<u32, Min, U8> = IndexedHeap::new();
+
+        let five = heap.push(5).unwrap();
+        let one = heap.push(1).unwrap();
+        let four = heap.push(4).unwrap();
+        heap.push(2).unwrap();
+        heap.push(8).unwrap();
+        let three = heap.push(3).unwrap();
+
+        // Root, inner node and leaf
+        assert_eq!(heap.remove(one), Some(1));
+        assert_eq!(heap.remove(four), Some(4));
+        assert_eq!(heap.remove(three), Some(3));
+        assert_eq!(heap.get(five), Some(&5));
+
+        // Stale keys don't match the reused nodes
+        heap.push(0).unwrap();
+        assert_eq!(heap.remove(one), None);
+        assert_eq!(heap.get(four), None);
+
+        assert_eq!(heap.pop(), Ok(0));
+        assert_eq!(heap.pop(), Ok(2));
+        assert_eq!(heap.pop(), Ok(5));
+        assert_eq!(heap.pop(), Ok(8));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_for_each_mut() {
+        let mut heap: IndexedHeap<u32, Min, U4> = IndexedHeap::new();
+
+        heap.push(3).unwrap();
+        let one = heap.push(1).unwrap();
+        heap.push(2).unwrap();
+
+        unsafe { heap.for_each_mut(|value| *value += 10) };
+
+        // Keys stay valid
+        assert_eq!(heap.get(one), Some(&11));
+        assert_eq!(heap.pop(), Ok(11));
+        assert_eq!(heap.pop(), Ok(12));
+        assert_eq!(heap.pop(), Ok(13));
+    }
+
+    #[test]
+    fn test_update_by_key() {
+        let mut heap: IndexedHeap<u32, Min, U8> = IndexedHeap::new();
+
+        let five = heap.push(5).unwrap();
+        let one = heap.push(1).unwrap();
+        heap.push(3).unwrap();
+        heap.push(7).unwrap();
+
+        // Towards the leaves, then back to the root
+        assert!(heap.update(one, |value| *value = 6));
+        assert_eq!(heap.peek(), Some(&3));
+        assert!(heap.update(five, |value| *value = 0));
+        assert_eq!(heap.peek(), Some(&0));
+
+        // Keys survive the update but not the removal
+        assert_eq!(heap.remove(one), Some(6));
+        assert!(!heap.update(one, |value| *value = 2));
+
+        assert_eq!(heap.pop(), Ok(0));
+        assert_eq!(heap.pop(), Ok(3));
+        assert_eq!(heap.pop(), Ok(7));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_equal_elements_keep_order() {
+        // Ordered by the first field only, the second one tags the elements
+        #[derive(Debug)]
+        struct Entry(u32, u32);
+
+        impl PartialEq for Entry {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl PartialOrd for Entry {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        let mut heap: IndexedHeap<Entry, Min, U16> = IndexedHeap::new();
+
+        let mut one = None;
+        for tag in 0..9 {
+            let key = heap.push(Entry(tag % 2, tag)).unwrap();
+            if tag == 1 {
+                one = Some(key);
+            }
+        }
+
+        // An updated element goes behind the ones it's equal to
+        assert!(heap.update(one.unwrap(), |_| {}));
+
+        for tag in &[0, 2, 4, 6, 8, 3, 5, 7, 1] {
+            assert_eq!(heap.pop().unwrap().1, *tag);
+        }
+        assert!(heap.is_empty());
+    }
+}
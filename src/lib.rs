@@ -33,20 +33,52 @@
 #![deny(rust_2018_compatibility)]
 #![deny(rust_2018_idioms)]
 #![deny(warnings)]
-#![no_std]
+#![cfg_attr(not(feature = "host"), no_std)]
 
-use cortex_m::{interrupt::InterruptNumber, peripheral::NVIC};
+pub use channel::{Receiver, Sender};
+use cortex_m::interrupt::InterruptNumber;
 pub use cortex_m_rtic_macros::app;
+pub use executor::Delay;
+use export::NVIC;
+#[cfg(feature = "lock-trace")]
+pub use lock_trace::{lock_stats, reset_lock_stats, LockStats};
+pub use pool::Box;
 pub use rtic_core::{prelude as mutex_prelude, Exclusive, Mutex};
 pub use rtic_monotonic::{self, embedded_time as time, Monotonic};
+#[cfg(feature = "stats")]
+pub use stats::{reset_stats, stats, QueueId, QueueStats};
 
+mod channel;
+#[doc(hidden)]
+mod edf;
+mod executor;
 #[doc(hidden)]
 pub mod export;
+#[cfg(feature = "host")]
+pub mod host;
+#[doc(hidden)]
+#[cfg_attr(not(feature = "tq-heap"), allow(dead_code))]
+mod indexed_heap;
 #[doc(hidden)]
+#[cfg_attr(feature = "tq-heap", allow(dead_code))]
 mod linked_list;
+#[cfg(feature = "lock-trace")]
+mod lock_trace;
+#[doc(hidden)]
+mod multiplex;
+mod pool;
+#[doc(hidden)]
+mod rq;
+#[cfg(feature = "stats")]
+mod stats;
+pub mod testing;
 #[doc(hidden)]
 mod tq;
 
+// Every `lock` would otherwise fail on the missing `export::cycle_count`
+#[cfg(all(armv6m, feature = "lock-trace"))]
+compile_error!("the `lock-trace` feature needs the DWT cycle counter, which ARMv6-M doesn't have");
+
 /// Sets the given `interrupt` as pending
 ///
 /// This is a convenience function around
@@ -82,6 +114,162 @@ impl<T> RacyCell<T> {
     pub unsafe fn get_unchecked(&self) -> &T {
         &*self.0.get()
     }
+
+    /// Get `*mut T`, without creating a reference that could alias another one
+    #[inline(always)]
+    pub fn get_mut(&self) -> *mut T {
+        self.0.get()
+    }
 }
 
 unsafe impl<T> Sync for RacyCell<T> {}
+
+/// Why spawning, cancelling or rescheduling a software task failed
+///
+/// The variants that stem from a spawn carry the payload back to the caller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawnError<T> {
+    /// All `capacity` instances of the task are already pending
+    Full(T),
+    /// The monotonic has not been handed over to the runtime yet, i.e. `#[init]` hasn't returned
+    MonotonicNotReady(T),
+    /// The `#[pool]` of the message has no free block left
+    PoolExhausted(T),
+    /// The instance has already been released for dispatch and can no longer be changed
+    AlreadyDispatched,
+    /// The instance was cancelled through another handle obtained from `spawn_or_reschedule_after`
+    Stale,
+}
+
+impl<T> SpawnError<T> {
+    /// Returns the payload, if the error carries one
+    pub fn into_inner(self) -> Option<T> {
+        match self {
+            SpawnError::Full(payload)
+            | SpawnError::MonotonicNotReady(payload)
+            | SpawnError::PoolExhausted(payload) => Some(payload),
+            SpawnError::AlreadyDispatched | SpawnError::Stale => None,
+        }
+    }
+
+    /// Maps the payload, if the error carries one
+    pub fn map<U, F>(self, f: F) -> SpawnError<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            SpawnError::Full(payload) => SpawnError::Full(f(payload)),
+            SpawnError::MonotonicNotReady(payload) => SpawnError::MonotonicNotReady(f(payload)),
+            SpawnError::PoolExhausted(payload) => SpawnError::PoolExhausted(f(payload)),
+            SpawnError::AlreadyDispatched => SpawnError::AlreadyDispatched,
+            SpawnError::Stale => SpawnError::Stale,
+        }
+    }
+}
+
+/// Lateness statistics of a software task with a `max_latency`
+///
+/// Collected by the timer queue handler of the default monotonic when it releases the task. All
+/// durations are in ticks of that monotonic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lateness {
+    /// Largest lateness seen so far
+    pub max: u64,
+    /// Number of releases that were later than `max_latency`
+    pub misses: u32,
+    /// Number of releases
+    pub releases: u32,
+}
+
+impl Lateness {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Lateness {
+            max: 0,
+            misses: 0,
+            releases: 0,
+        }
+    }
+
+    /// Records a release; returns `true` if it missed the `max_latency` bound
+    #[doc(hidden)]
+    pub fn record(&mut self, lateness: u64, max_latency: u64) -> bool {
+        self.max = self.max.max(lateness);
+        self.releases = self.releases.saturating_add(1);
+
+        if lateness > max_latency {
+            self.misses = self.misses.saturating_add(1);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Execution time statistics of a task with a `budget`
+///
+/// Collected around every run of the task. Times are in ticks of the default monotonic for
+/// `budget`, or in core clock cycles for `budget_cycles`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionTime {
+    /// Longest run seen so far
+    pub max: u64,
+    /// Number of runs that took longer than the budget
+    pub overruns: u32,
+    /// Number of runs
+    pub runs: u32,
+}
+
+impl ExecutionTime {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        ExecutionTime {
+            max: 0,
+            overruns: 0,
+            runs: 0,
+        }
+    }
+
+    /// Records a run; returns `true` if it overran the budget
+    #[doc(hidden)]
+    pub fn record(&mut self, time: u64, budget: u64) -> bool {
+        self.max = self.max.max(time);
+        self.runs = self.runs.saturating_add(1);
+
+        if time > budget {
+            self.overruns = self.overruns.saturating_add(1);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Hooks that are told when tasks run, named with `#[rtic::app(tracer = ..)]`
+///
+/// Tasks are identified by the `ID` constant in their module, and the timer queue handlers by the
+/// one in `monotonics::<Mono>`; `TASK_NAMES` in the app module maps the IDs back to names. The
+/// hooks run in the context of the task, at its priority, so they must be short. All of them do
+/// nothing by default.
+pub trait Tracer {
+    /// Called right before a task runs
+    ///
+    /// Hardware tasks run when their interrupt is serviced, software tasks when their dispatcher
+    /// picks them up and `async` tasks every time they're polled.
+    fn task_enter(_task: u16) {}
+
+    /// Called right after a task has returned (or an `async` task has been polled)
+    fn task_exit(_task: u16) {}
+
+    /// Called when an instance of a software task is made ready to be dispatched, by a spawn or by
+    /// its release from a timer queue
+    ///
+    /// This hook runs within a critical section.
+    fn spawn(_task: u16) {}
+
+    /// Called when `Mutex::lock` lowers the priority back from the ceiling of a resource, with the
+    /// length of the critical section in core clock cycles (`lock-trace` feature)
+    ///
+    /// Resources are identified by their position in `RESOURCE_NAMES`, in the app module.
+    fn lock(_resource: u16, _ceiling: u8, _cycles: u32) {}
+}
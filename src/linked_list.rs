@@ -34,6 +34,18 @@ impl LinkedIndex {
 pub struct Node<T> {
     val: MaybeUninit<T>,
     next: LinkedIndex,
+    prev: LinkedIndex,
+    generation: u32,
+}
+
+/// Identifies an element for as long as it stays in the list.
+///
+/// The generation of a node is bumped every time the node is released, so a key to an element
+/// that has been popped or removed never matches a newer element stored in the same node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    index: LinkedIndex,
+    generation: u32,
 }
 
 /// Iterator for the linked list.
@@ -73,7 +85,6 @@ where
     N: ArrayLength<Node<T>>,
 {
     list: &'a mut LinkedList<T, Kind, N>,
-    is_head: bool,
     prev_index: LinkedIndex,
     index: LinkedIndex,
     maybe_changed: bool,
@@ -86,22 +97,8 @@ where
     N: ArrayLength<Node<T>>,
 {
     fn pop_internal(&mut self) -> T {
-        if self.is_head {
-            // If it is the head element, we can do a normal pop
-            unsafe { self.list.pop_unchecked() }
-        } else {
-            // Somewhere in the list
-
-            // Re-point the previous index
-            self.list.node_at_mut(self.prev_index.0 as usize).next =
-                self.list.node_at_mut(self.index.0 as usize).next;
-
-            // Release the index into the free queue
-            self.list.node_at_mut(self.index.0 as usize).next = self.list.free;
-            self.list.free = self.index;
-
-            self.list.extract_data_in_node_at(self.index.0 as usize)
-        }
+        // Safety: `self.index` points to an element of the list
+        unsafe { self.list.remove_unchecked(self.index.0) }
     }
 
     /// This will pop the element from the list.
@@ -197,7 +194,9 @@ where
 {
     list: MaybeUninit<GenericArray<Node<T>, N>>,
     head: LinkedIndex,
+    tail: LinkedIndex,
     free: LinkedIndex,
+    len: u16,
     _kind: PhantomData<Kind>,
 }
 
@@ -262,7 +261,9 @@ where
         let mut list = LinkedList {
             list: MaybeUninit::uninit(),
             head: LinkedIndex::none(),
+            tail: LinkedIndex::none(),
             free: unsafe { LinkedIndex::new_unchecked(0) },
+            len: 0,
             _kind: PhantomData,
         };
 
@@ -282,6 +283,8 @@ where
                     Node {
                         val: MaybeUninit::uninit(),
                         next: LinkedIndex::new_unchecked(free + 1),
+                        prev: LinkedIndex::none(),
+                        generation: 0,
                     },
                 );
             }
@@ -295,6 +298,8 @@ where
                 Node {
                     val: MaybeUninit::uninit(),
                     next: LinkedIndex::none(),
+                    prev: LinkedIndex::none(),
+                    generation: 0,
                 },
             );
         }
@@ -309,30 +314,59 @@ where
     /// # Safety
     ///
     /// Assumes that the list is not full.
-    pub unsafe fn push_unchecked(&mut self, value: T) {
+    pub unsafe fn push_unchecked(&mut self, value: T) -> Key {
         let new = self.free.0;
         // Store the data and update the next free spot
         self.write_data_in_node_at(new as usize, value);
         self.free = self.node_at(new as usize).next;
+        self.len += 1;
+
+        self.link(new);
+
+        Key {
+            index: LinkedIndex::new_unchecked(new),
+            generation: self.node_at(new as usize).generation,
+        }
+    }
 
+    /// Internal helper: sorts the unlinked node `new` into the list.
+    ///
+    /// Complexity is O(N), O(1) if `new` goes to the head or to the tail.
+    ///
+    /// # Safety
+    ///
+    /// `new` must hold an element and must not be linked into the list.
+    unsafe fn link(&mut self, new: u16) {
         if let Some(head) = self.head.option() {
-            // Check if we need to replace head
+            // Check if we need to replace head, equal elements keep their insertion order
             if self
-                .read_data_in_node_at(head as usize)
-                .partial_cmp(self.read_data_in_node_at(new as usize))
-                != Kind::ordering()
+                .read_data_in_node_at(new as usize)
+                .partial_cmp(self.read_data_in_node_at(head as usize))
+                == Kind::ordering()
             {
+                self.node_at_mut(head as usize).prev = LinkedIndex::new_unchecked(new);
                 self.node_at_mut(new as usize).next = self.head;
+                self.node_at_mut(new as usize).prev = LinkedIndex::none();
                 self.head = LinkedIndex::new_unchecked(new);
+            } else if self
+                .read_data_in_node_at(new as usize)
+                .partial_cmp(self.read_data_in_node_at(self.tail.0 as usize))
+                != Kind::ordering()
+            {
+                // Not before the tail, e.g. in a list of equal elements, append it
+                self.node_at_mut(self.tail.0 as usize).next = LinkedIndex::new_unchecked(new);
+                self.node_at_mut(new as usize).next = LinkedIndex::none();
+                self.node_at_mut(new as usize).prev = self.tail;
+                self.tail = LinkedIndex::new_unchecked(new);
             } else {
-                // It's not head, search the list for the correct placement
+                // It's neither head nor tail, search the list for the correct placement
                 let mut current = head;
 
                 while let Some(next) = self.node_at(current as usize).next.option() {
                     if self
-                        .read_data_in_node_at(next as usize)
-                        .partial_cmp(self.read_data_in_node_at(new as usize))
-                        != Kind::ordering()
+                        .read_data_in_node_at(new as usize)
+                        .partial_cmp(self.read_data_in_node_at(next as usize))
+                        == Kind::ordering()
                     {
                         break;
                     }
@@ -340,19 +374,26 @@ where
                     current = next;
                 }
 
-                self.node_at_mut(new as usize).next = self.node_at(current as usize).next;
+                let next = self.node_at(current as usize).next;
+                if let Some(next) = next.option() {
+                    self.node_at_mut(next as usize).prev = LinkedIndex::new_unchecked(new);
+                }
+                self.node_at_mut(new as usize).next = next;
+                self.node_at_mut(new as usize).prev = LinkedIndex::new_unchecked(current);
                 self.node_at_mut(current as usize).next = LinkedIndex::new_unchecked(new);
             }
         } else {
             self.node_at_mut(new as usize).next = self.head;
+            self.node_at_mut(new as usize).prev = LinkedIndex::none();
             self.head = LinkedIndex::new_unchecked(new);
+            self.tail = LinkedIndex::new_unchecked(new);
         }
     }
 
     /// Pushes an element to the linked list and sorts it into place.
     ///
     /// Complexity is O(N).
-    pub fn push(&mut self, value: T) -> Result<(), T> {
+    pub fn push(&mut self, value: T) -> Result<Key, T> {
         if !self.is_full() {
             Ok(unsafe { self.push_unchecked(value) })
         } else {
@@ -378,7 +419,6 @@ where
         // Special-case, first element
         if f(self.read_data_in_node_at(head as usize)) {
             return Some(FindMut {
-                is_head: true,
                 prev_index: LinkedIndex::none(),
                 index: self.head,
                 list: self,
@@ -391,7 +431,6 @@ where
         while let Some(next) = self.node_at(current as usize).next.option() {
             if f(self.read_data_in_node_at(next as usize)) {
                 return Some(FindMut {
-                    is_head: false,
                     prev_index: unsafe { LinkedIndex::new_unchecked(current) },
                     index: unsafe { LinkedIndex::new_unchecked(next) },
                     list: self,
@@ -405,6 +444,173 @@ where
         None
     }
 
+    /// Get the key of the first element that satisfies `f`.
+    ///
+    /// Complexity is O(N).
+    pub fn find_key<F>(&self, f: F) -> Option<Key>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.search(self.head, f, |node| node.next)
+    }
+
+    /// Get the key of the last element that satisfies `f`, searching from the tail.
+    ///
+    /// Complexity is O(N).
+    pub fn rfind_key<F>(&self, f: F) -> Option<Key>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.search(self.tail, f, |node| node.prev)
+    }
+
+    /// Internal helper: walks the list from `index` in the direction of `step`.
+    fn search<F, S>(&self, mut index: LinkedIndex, mut f: F, step: S) -> Option<Key>
+    where
+        F: FnMut(&T) -> bool,
+        S: Fn(&Node<T>) -> LinkedIndex,
+    {
+        while let Some(current) = index.option() {
+            let node = self.node_at(current as usize);
+
+            if f(self.read_data_in_node_at(current as usize)) {
+                return Some(Key {
+                    index,
+                    generation: node.generation,
+                });
+            }
+
+            index = step(node);
+        }
+
+        None
+    }
+
+    /// Get the element identified by `key`, if it's still in the list.
+    ///
+    /// Complexity is O(1).
+    pub fn get(&self, key: Key) -> Option<&T> {
+        if self.is_live(key) {
+            Some(self.read_data_in_node_at(key.index.0 as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Remove the element identified by `key`, if it's still in the list.
+    ///
+    /// Complexity is O(1).
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        if self.is_live(key) {
+            // Safety: a matching generation means the node has not been released since `key` was
+            // handed out, so it's still linked into the list
+            Some(unsafe { self.remove_unchecked(key.index.0) })
+        } else {
+            None
+        }
+    }
+
+    /// Change the element identified by `key` with `f` and move it to its new place.
+    ///
+    /// The key stays valid. Returns `false` if `key` no longer refers to an element.
+    ///
+    /// Complexity is O(N).
+    pub fn update<F>(&mut self, key: Key, f: F) -> bool
+    where
+        F: FnOnce(&mut T),
+    {
+        if !self.is_live(key) {
+            return false;
+        }
+
+        let index = key.index.0;
+        unsafe {
+            self.unlink(index);
+            f(self.read_mut_data_in_node_at(index as usize));
+            self.link(index);
+        }
+
+        true
+    }
+
+    /// Internal helper: checks that `key` still refers to an element of the list.
+    #[inline]
+    fn is_live(&self, key: Key) -> bool {
+        match key.index.option() {
+            Some(index) if (index as usize) < N::USIZE => {
+                self.node_at(index as usize).generation == key.generation
+            }
+            _ => false,
+        }
+    }
+
+    /// Internal helper: unlinks the element at `index` and releases its node.
+    ///
+    /// Complexity is O(1).
+    ///
+    /// # Safety
+    ///
+    /// `index` must point to an element of the list.
+    unsafe fn remove_unchecked(&mut self, index: u16) -> T {
+        self.unlink(index);
+
+        // Release the index into the free queue and invalidate outstanding keys
+        let free = self.free;
+        let node = self.node_at_mut(index as usize);
+        node.next = free;
+        node.generation = node.generation.wrapping_add(1);
+        self.free = LinkedIndex::new_unchecked(index);
+        self.len -= 1;
+
+        self.extract_data_in_node_at(index as usize)
+    }
+
+    /// Internal helper: takes the node at `index` out of the list, without releasing it.
+    ///
+    /// Complexity is O(1).
+    ///
+    /// # Safety
+    ///
+    /// `index` must point to an element of the list.
+    unsafe fn unlink(&mut self, index: u16) {
+        let (prev, next) = {
+            let node = self.node_at(index as usize);
+            (node.prev, node.next)
+        };
+
+        // Re-point the neighbours
+        if let Some(prev) = prev.option() {
+            self.node_at_mut(prev as usize).next = next;
+        } else {
+            self.head = next;
+        }
+
+        if let Some(next) = next.option() {
+            self.node_at_mut(next as usize).prev = prev;
+        } else {
+            self.tail = prev;
+        }
+    }
+
+    /// Calls `f` on every element, in order.
+    ///
+    /// Complexity is O(N).
+    ///
+    /// # Safety
+    ///
+    /// `f` must not change the relative order of the elements.
+    pub unsafe fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T),
+    {
+        let mut index = self.head;
+
+        while let Some(current) = index.option() {
+            f(self.read_mut_data_in_node_at(current as usize));
+            index = self.node_at(current as usize).next;
+        }
+    }
+
     /// Peek at the first element.
     pub fn peek(&self) -> Option<&T> {
         self.head
@@ -412,19 +618,21 @@ where
             .map(|head| self.read_data_in_node_at(head as usize))
     }
 
+    /// Get the key of the first element.
+    pub fn peek_key(&self) -> Option<Key> {
+        self.head.option().map(|head| Key {
+            index: self.head,
+            generation: self.node_at(head as usize).generation,
+        })
+    }
+
     /// Pop unchecked
     ///
     /// # Safety
     ///
     /// Assumes that the list is not empty.
     pub unsafe fn pop_unchecked(&mut self) -> T {
-        let head = self.head.0;
-        let current = head;
-        self.head = self.node_at(head as usize).next;
-        self.node_at_mut(current as usize).next = self.free;
-        self.free = LinkedIndex::new_unchecked(current);
-
-        self.extract_data_in_node_at(current as usize)
+        self.remove_unchecked(self.head.0)
     }
 
     /// Pops the first element in the list.
@@ -449,6 +657,12 @@ where
     pub fn is_empty(&self) -> bool {
         self.head.option().is_none()
     }
+
+    /// The number of elements in the list.
+    #[inline]
+    pub fn len(&self) -> usize {
+        usize::from(self.len)
+    }
 }
 
 impl<T, Kind, N> Drop for LinkedList<T, Kind, N>
@@ -552,7 +766,8 @@ mod tests {
         ll.push(2).unwrap();
         ll.push(3).unwrap();
 
-        assert!(ll.is_full())
+        assert!(ll.is_full());
+        assert_eq!(ll.len(), 3);
     }
 
     #[test]
@@ -609,4 +824,150 @@ mod tests {
 
         assert_eq!(ll.peek().unwrap(), &1002);
     }
+
+    #[test]
+    fn test_stable_push() {
+        // Only the first field takes part in the ordering
+        #[derive(Debug)]
+        struct Entry(u32, u32);
+
+        impl PartialEq for Entry {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl PartialOrd for Entry {
+            fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+
+        let mut ll: LinkedList<Entry, Min, U4> = LinkedList::new();
+        ll.push(Entry(1, 0)).unwrap();
+        ll.push(Entry(0, 1)).unwrap();
+        ll.push(Entry(1, 2)).unwrap();
+        ll.push(Entry(0, 3)).unwrap();
+
+        let mut iter = ll.iter().map(|entry| entry.1);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_remove_by_key() {
+        let mut ll: LinkedList<u32, Min, U3> = LinkedList::new();
+        let one = ll.push(1).unwrap();
+        let two = ll.push(2).unwrap();
+        let three = ll.push(3).unwrap();
+
+        // Middle, tail and head
+        assert_eq!(ll.remove(two), Some(2));
+        assert_eq!(ll.remove(three), Some(3));
+        assert_eq!(ll.get(one), Some(&1));
+        assert_eq!(ll.remove(one), Some(1));
+        assert!(ll.is_empty());
+        assert_eq!(ll.len(), 0);
+
+        // The nodes are reused but the old keys stay stale
+        let four = ll.push(4).unwrap();
+        assert_eq!(ll.remove(one), None);
+        assert_eq!(ll.get(two), None);
+        assert_eq!(ll.peek(), Some(&4));
+
+        ll.push(0).unwrap();
+        ll.push(5).unwrap();
+        assert_eq!(ll.remove(four), Some(4));
+        let mut iter = ll.iter();
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next(), Some(&5));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_update_by_key() {
+        let mut ll: LinkedList<u32, Min, U3> = LinkedList::new();
+        let one = ll.push(1).unwrap();
+        let two = ll.push(2).unwrap();
+        ll.push(3).unwrap();
+
+        // Head to tail, then back to the head
+        assert!(ll.update(one, |value| *value = 4));
+        assert_eq!(ll.peek(), Some(&2));
+        assert!(ll.update(one, |value| *value = 0));
+        assert_eq!(ll.peek(), Some(&0));
+
+        // Keys survive the update but not the removal
+        assert_eq!(ll.remove(two), Some(2));
+        assert!(!ll.update(two, |value| *value = 5));
+
+        let mut iter = ll.iter();
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_tail() {
+        let mut ll: LinkedList<u32, Min, U4> = LinkedList::new();
+        ll.push(1).unwrap();
+        let three = ll.push(3).unwrap();
+
+        // Removing the tail moves it back, later pushes are still appended in place
+        assert_eq!(ll.remove(three), Some(3));
+        ll.push(2).unwrap();
+        ll.push(2).unwrap();
+        ll.push(0).unwrap();
+
+        assert_eq!(ll.peek_key().and_then(|key| ll.get(key)), Some(&0));
+        let mut iter = ll.iter();
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), None);
+
+        // Emptying the list resets the tail
+        while ll.pop().is_ok() {}
+        ll.push(5).unwrap();
+        ll.push(4).unwrap();
+        assert_eq!(ll.pop(), Ok(4));
+        assert_eq!(ll.pop(), Ok(5));
+    }
+
+    #[test]
+    fn test_find_key() {
+        let mut ll: LinkedList<u32, Min, U4> = LinkedList::new();
+        ll.push(1).unwrap();
+        ll.push(2).unwrap();
+        ll.push(3).unwrap();
+        ll.push(4).unwrap();
+
+        let odd = |value: &u32| value % 2 == 1;
+        assert_eq!(ll.find_key(odd).and_then(|key| ll.get(key)), Some(&1));
+        assert_eq!(ll.rfind_key(odd).and_then(|key| ll.get(key)), Some(&3));
+        assert_eq!(ll.find_key(|value| *value > 4), None);
+        assert_eq!(ll.rfind_key(|value| *value > 4), None);
+    }
+
+    #[test]
+    fn test_for_each_mut() {
+        let mut ll: LinkedList<u32, Min, U3> = LinkedList::new();
+        ll.push(2).unwrap();
+        let one = ll.push(1).unwrap();
+        ll.push(3).unwrap();
+
+        unsafe { ll.for_each_mut(|value| *value += 10) };
+
+        // Keys stay valid
+        assert_eq!(ll.get(one), Some(&11));
+        let mut iter = ll.iter();
+        assert_eq!(iter.next(), Some(&11));
+        assert_eq!(iter.next(), Some(&12));
+        assert_eq!(iter.next(), Some(&13));
+        assert_eq!(iter.next(), None);
+    }
 }
@@ -0,0 +1,95 @@
+//! Critical section times of the resource locks (`lock-trace` feature)
+//!
+//! Every resource that is accessed through `Mutex::lock` gets a `Counter`, in a `static`
+//! generated by the `app` macro, that keeps the longest critical section seen so far. The times
+//! are measured with the DWT cycle counter; the counters are registered before `#[init]` runs.
+
+use core::cell::Cell;
+
+use crate::{export::interrupt, RacyCell};
+
+/// Critical section times of a resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockStats {
+    /// The resource
+    pub resource: &'static str,
+    /// The priority ceiling of the resource
+    pub ceiling: u8,
+    /// How many times the priority was raised to lock the resource
+    pub locks: u32,
+    /// The longest critical section, in core clock cycles
+    pub max_cycles: u32,
+}
+
+/// Critical section times of a resource, as they're being recorded
+#[doc(hidden)]
+pub struct Counter {
+    stats: Cell<LockStats>,
+}
+
+// NOTE all the accesses happen within critical sections
+unsafe impl Sync for Counter {}
+
+impl Counter {
+    /// A counter for `resource`, whose ceiling is `ceiling`
+    pub const fn new(resource: &'static str, ceiling: u8) -> Self {
+        Counter {
+            stats: Cell::new(LockStats {
+                resource,
+                ceiling,
+                locks: 0,
+                max_cycles: 0,
+            }),
+        }
+    }
+
+    /// Records a critical section that lasted `cycles`
+    #[inline]
+    pub fn record(&self, cycles: u32) {
+        interrupt::free(|_| {
+            let mut stats = self.stats.get();
+            stats.locks = stats.locks.saturating_add(1);
+            stats.max_cycles = stats.max_cycles.max(cycles);
+            self.stats.set(stats);
+        })
+    }
+
+    fn get(&self) -> LockStats {
+        interrupt::free(|_| self.stats.get())
+    }
+
+    fn reset(&self) {
+        interrupt::free(|_| {
+            let mut stats = self.stats.get();
+            stats.locks = 0;
+            stats.max_cycles = 0;
+            self.stats.set(stats);
+        })
+    }
+}
+
+static COUNTERS: RacyCell<&'static [&'static Counter]> = RacyCell::new(&[]);
+
+/// Makes `counters` the ones `lock_stats` reports
+#[doc(hidden)]
+pub unsafe fn register(counters: &'static [&'static Counter]) {
+    *COUNTERS.get_mut_unchecked() = counters;
+}
+
+/// Returns the critical section times of all the resources that are locked
+///
+/// Resources come in the order they were declared. Locks that didn't need to raise the priority,
+/// because the task already ran at the ceiling, are not counted.
+pub fn lock_stats() -> impl Iterator<Item = LockStats> {
+    counters().iter().map(|counter| counter.get())
+}
+
+/// Clears the lock counts and the longest critical sections of all the resources
+pub fn reset_lock_stats() {
+    counters().iter().for_each(|counter| counter.reset())
+}
+
+fn counters() -> &'static [&'static Counter] {
+    // NOTE only written in `pre_init`, with interrupts disabled
+    unsafe { *COUNTERS.get_unchecked() }
+}
@@ -0,0 +1,63 @@
+use core::cell::Cell;
+
+use bare_metal::CriticalSection;
+
+/// State of a dispatcher that serves several priority levels
+///
+/// The dispatcher runs at the priority of the level it's dispatching. While it's idle its
+/// priority is kept at the highest pending level, so that it's entered at that level.
+pub struct Multiplexer {
+    // Bit `n - 1` is set while level `n` has tasks to dispatch
+    pending: Cell<u32>,
+    running: Cell<bool>,
+}
+
+// NOTE all the accesses happen within critical sections
+unsafe impl Sync for Multiplexer {}
+
+impl Multiplexer {
+    pub const fn new() -> Self {
+        Multiplexer {
+            pending: Cell::new(0),
+            running: Cell::new(false),
+        }
+    }
+
+    /// Marks `level` as pending
+    ///
+    /// Returns the level the dispatcher must be set to before it's pended, or `None` if it's
+    /// running and will pick the level up on its own.
+    #[inline]
+    pub fn pend(&self, level: u8, _cs: &CriticalSection) -> Option<u8> {
+        let pending = self.pending.get() | 1 << (level - 1);
+        self.pending.set(pending);
+
+        if self.running.get() {
+            None
+        } else {
+            Some(highest(pending))
+        }
+    }
+
+    /// Takes the highest pending level, if any; the dispatcher is idle once this returns `None`
+    #[inline]
+    pub fn next(&self, _cs: &CriticalSection) -> Option<u8> {
+        let pending = self.pending.get();
+
+        if pending == 0 {
+            self.running.set(false);
+
+            None
+        } else {
+            let level = highest(pending);
+            self.pending.set(pending & !(1 << (level - 1)));
+            self.running.set(true);
+
+            Some(level)
+        }
+    }
+}
+
+fn highest(pending: u32) -> u8 {
+    (32 - pending.leading_zeros()) as u8
+}
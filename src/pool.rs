@@ -0,0 +1,149 @@
+//! Fixed-size memory pools declared with `#[pool(T, N)]`
+//!
+//! The blocks of a pool live in a `static` that the `app` macro sizes; they're handed over to the
+//! pool before `#[init]` runs. Blocks are taken and given back within critical sections, so a
+//! `Box` can be allocated in one task and dropped in another.
+
+use core::{
+    cell::Cell,
+    fmt,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use crate::export::interrupt;
+
+/// A block of a pool
+pub struct Node<T> {
+    next: *mut Node<T>,
+    data: MaybeUninit<T>,
+}
+
+/// A pool of blocks that each hold a `T`
+pub struct Pool<T> {
+    // Stack of the free blocks
+    head: Cell<*mut Node<T>>,
+    available: Cell<usize>,
+}
+
+// NOTE all the accesses happen within critical sections
+unsafe impl<T> Sync for Pool<T> {}
+
+impl<T> Pool<T> {
+    pub const fn new() -> Self {
+        Pool {
+            head: Cell::new(ptr::null_mut()),
+            available: Cell::new(0),
+        }
+    }
+
+    /// Hands the `len` blocks that start at `nodes` over to the pool
+    ///
+    /// # Safety
+    ///
+    /// The blocks must not be used for anything else, ever
+    pub unsafe fn manage(&self, nodes: *mut Node<T>, len: usize) {
+        for i in 0..len {
+            self.push(nodes.add(i));
+        }
+    }
+
+    /// Moves `value` into a free block, or hands it back if there's none
+    pub fn alloc(&'static self, value: T) -> Result<Box<T>, T> {
+        let node = interrupt::free(|_| {
+            let head = self.head.get();
+
+            NonNull::new(head).map(|node| {
+                self.head.set(unsafe { (*head).next });
+                self.available.set(self.available.get() - 1);
+
+                node
+            })
+        });
+
+        match node {
+            Some(node) => unsafe {
+                (*node.as_ptr()).data.as_mut_ptr().write(value);
+
+                Ok(Box { node, pool: self })
+            },
+
+            None => Err(value),
+        }
+    }
+
+    /// The number of free blocks
+    pub fn available(&self) -> usize {
+        interrupt::free(|_| self.available.get())
+    }
+
+    unsafe fn push(&self, node: *mut Node<T>) {
+        interrupt::free(|_| {
+            (*node).next = self.head.get();
+            self.head.set(node);
+            self.available.set(self.available.get() + 1);
+        })
+    }
+}
+
+/// Owning pointer to a block of a `#[pool]`
+///
+/// The block goes back to its pool when the `Box` is dropped.
+pub struct Box<T>
+where
+    T: 'static,
+{
+    node: NonNull<Node<T>>,
+    pool: &'static Pool<T>,
+}
+
+unsafe impl<T> Send for Box<T> where T: Send {}
+
+unsafe impl<T> Sync for Box<T> where T: Sync {}
+
+impl<T> Box<T> {
+    /// Moves the value out of the box, giving the block back to the pool
+    pub fn into_inner(boxed: Self) -> T {
+        let boxed = ManuallyDrop::new(boxed);
+
+        unsafe {
+            let value = (*boxed.node.as_ptr()).data.as_ptr().read();
+            boxed.pool.push(boxed.node.as_ptr());
+
+            value
+        }
+    }
+}
+
+impl<T> Deref for Box<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*self.node.as_ptr()).data.as_ptr() }
+    }
+}
+
+impl<T> DerefMut for Box<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *(*self.node.as_ptr()).data.as_mut_ptr() }
+    }
+}
+
+impl<T> Drop for Box<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place((*self.node.as_ptr()).data.as_mut_ptr());
+            self.pool.push(self.node.as_ptr());
+        }
+    }
+}
+
+impl<T> fmt::Debug for Box<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        T::fmt(self, f)
+    }
+}
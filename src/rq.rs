@@ -0,0 +1,84 @@
+use crate::linked_list::{ArrayLength, Key, LinkedList, Min, Node};
+use core::cmp::Ordering;
+
+/// Ready queue of a dispatcher: first in, first out
+///
+/// Unlike a ring buffer any entry can be taken out again before it's dispatched.
+pub struct ReadyQueue<Task, N>(pub LinkedList<Ready<Task>, Min, N>)
+where
+    N: ArrayLength<Node<Ready<Task>>>,
+    Task: Copy;
+
+impl<Task, N> ReadyQueue<Task, N>
+where
+    N: ArrayLength<Node<Ready<Task>>>,
+    Task: Copy,
+{
+    pub fn new() -> Self {
+        ReadyQueue(LinkedList::new())
+    }
+
+    /// Enqueue a task without checking if the queue is full
+    ///
+    /// Complexity is O(1).
+    ///
+    /// # Safety
+    ///
+    /// The queue must not be full.
+    #[inline]
+    pub unsafe fn enqueue_unchecked(&mut self, task: Task, index: u8) -> Key {
+        // All entries compare equal, so the list appends them at its tail
+        self.0.push_unchecked(Ready { task, index })
+    }
+
+    /// Dequeue the task that was enqueued first
+    #[inline]
+    pub fn dequeue(&mut self) -> Option<(Task, u8)> {
+        self.0.pop().ok().map(|r| (r.task, r.index))
+    }
+
+    /// Cancel the task identified by `key`
+    ///
+    /// Complexity is O(1).
+    #[inline]
+    pub fn cancel(&mut self, key: Key) -> Option<(Task, u8)> {
+        self.0.remove(key).map(|r| (r.task, r.index))
+    }
+}
+
+pub struct Ready<Task>
+where
+    Task: Copy,
+{
+    pub task: Task,
+    pub index: u8,
+}
+
+impl<Task> Eq for Ready<Task> where Task: Copy {}
+
+impl<Task> Ord for Ready<Task>
+where
+    Task: Copy,
+{
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl<Task> PartialEq for Ready<Task>
+where
+    Task: Copy,
+{
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<Task> PartialOrd for Ready<Task>
+where
+    Task: Copy,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
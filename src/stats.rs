@@ -0,0 +1,122 @@
+//! Occupancy statistics of the queues of the runtime (`stats` feature)
+//!
+//! Every queue gets a `Counter`, in a `static` generated by the `app` macro, that is updated
+//! whenever the queue grows. The counters are registered before `#[init]` runs.
+
+use core::cell::Cell;
+
+use crate::{export::interrupt, RacyCell};
+
+/// A queue of the runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueId {
+    /// The message slots (free queue) of the named software task
+    Free(&'static str),
+    /// The ready queue of a priority level
+    Ready(u8),
+    /// The timer queue of the named monotonic
+    Timer(&'static str),
+}
+
+/// Statistics of a queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueStats {
+    /// The queue
+    pub queue: QueueId,
+    /// How many entries the queue holds
+    pub capacity: usize,
+    /// The highest number of entries in use at once
+    pub high_watermark: usize,
+    /// How many times the queue had no room left
+    ///
+    /// Only the free queues run out of room: the ready and timer queues have an entry for every
+    /// message slot. A `delay` that doesn't fit in the timer queue is counted too.
+    pub failures: u32,
+}
+
+/// Statistics of a queue, as they're being recorded
+#[doc(hidden)]
+pub struct Counter {
+    stats: Cell<QueueStats>,
+}
+
+// NOTE all the accesses happen within critical sections
+unsafe impl Sync for Counter {}
+
+impl Counter {
+    /// A counter for `queue`, which holds `capacity` entries
+    pub const fn new(queue: QueueId, capacity: usize) -> Self {
+        Counter {
+            stats: Cell::new(QueueStats {
+                queue,
+                capacity,
+                high_watermark: 0,
+                failures: 0,
+            }),
+        }
+    }
+
+    /// Records that `len` entries are in use
+    #[inline]
+    pub fn record(&self, len: usize) {
+        interrupt::free(|_| {
+            let mut stats = self.stats.get();
+            if len > stats.high_watermark {
+                stats.high_watermark = len;
+                self.stats.set(stats);
+            }
+        })
+    }
+
+    /// Records that the queue had no room left
+    #[inline]
+    pub fn fail(&self) {
+        interrupt::free(|_| {
+            let mut stats = self.stats.get();
+            stats.failures = stats.failures.saturating_add(1);
+            self.stats.set(stats);
+        })
+    }
+
+    fn get(&self) -> QueueStats {
+        interrupt::free(|_| self.stats.get())
+    }
+
+    fn reset(&self) {
+        interrupt::free(|_| {
+            let mut stats = self.stats.get();
+            stats.high_watermark = 0;
+            stats.failures = 0;
+            self.stats.set(stats);
+        })
+    }
+}
+
+static COUNTERS: RacyCell<&'static [&'static Counter]> = RacyCell::new(&[]);
+
+/// Makes `counters` the ones `stats` reports
+#[doc(hidden)]
+pub unsafe fn register(counters: &'static [&'static Counter]) {
+    *COUNTERS.get_mut_unchecked() = counters;
+}
+
+/// Returns the statistics of all the queues of the application
+///
+/// Free queues come first, in the order the tasks were declared, then the ready queues, from the
+/// lowest priority up, and then the timer queues. Each entry is read in a critical section of its
+/// own.
+pub fn stats() -> impl Iterator<Item = QueueStats> {
+    counters().iter().map(|counter| counter.get())
+}
+
+/// Clears the high watermarks and the failure counts of all the queues
+///
+/// The high watermarks start over from the current occupancy once the queues grow again.
+pub fn reset_stats() {
+    counters().iter().for_each(|counter| counter.reset())
+}
+
+fn counters() -> &'static [&'static Counter] {
+    // NOTE only written in `pre_init`, with interrupts disabled
+    unsafe { *COUNTERS.get_unchecked() }
+}
@@ -0,0 +1,189 @@
+//! Utilities for testing RTIC applications
+//!
+//! These are mainly meant to be used together with the host backend (`host` feature), but they
+//! only rely on `rtic::pend` and work on the target as well.
+
+use cortex_m::interrupt::InterruptNumber;
+
+use crate::{
+    export::{interrupt, SCB},
+    time::{duration::Duration, fixed_point::FixedPoint, fraction::Fraction, Clock, Instant},
+    Monotonic, RacyCell,
+};
+
+/// The vector a `MockMonotonic` is bound to
+#[derive(Clone, Copy)]
+enum Vector {
+    SysTick,
+    Interrupt(u16),
+}
+
+impl Vector {
+    fn pend(self) {
+        match self {
+            Vector::SysTick => SCB::set_pendst(),
+            Vector::Interrupt(number) => crate::pend(Irq(number)),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Irq(u16);
+
+unsafe impl InterruptNumber for Irq {
+    fn number(self) -> u16 {
+        self.0
+    }
+}
+
+struct State {
+    now: u64,
+    compare: Option<u64>,
+    vector: Option<Vector>,
+    free_running: bool,
+}
+
+// NOTE all `MockMonotonic`s share a single virtual clock
+static STATE: RacyCell<State> = RacyCell::new(State {
+    now: 0,
+    compare: None,
+    vector: None,
+    free_running: false,
+});
+
+/// A `Monotonic` driven by a manually advanced, virtual clock that ticks at `FREQ` Hz
+///
+/// Bind it like any other monotonic and pass the same vector to the constructor, for example:
+///
+/// ``` ignore
+/// #[monotonic(binds = UART0, default = true)]
+/// type MyMono = MockMonotonic<1_000>;
+///
+/// // in `#[init]`
+/// (init::LateResources {}, init::Monotonics(MyMono::new(Interrupt::UART0)))
+/// ```
+///
+/// Time only moves forward when [`MockMonotonic::advance`] is called, which makes the timer queue
+/// fully deterministic. Only one `MockMonotonic` may be in use at a time.
+pub struct MockMonotonic<const FREQ: u32> {
+    _0: (),
+}
+
+impl<const FREQ: u32> MockMonotonic<FREQ> {
+    /// Creates a monotonic bound to the device `interrupt`
+    pub fn new<I>(interrupt: I) -> Self
+    where
+        I: InterruptNumber,
+    {
+        Self::bind(Vector::Interrupt(interrupt.number()))
+    }
+
+    /// Creates a monotonic bound to the `SysTick` exception
+    pub fn systick() -> Self {
+        Self::bind(Vector::SysTick)
+    }
+
+    fn bind(vector: Vector) -> Self {
+        interrupt::free(|_| unsafe {
+            *STATE.get_mut_unchecked() = State {
+                now: 0,
+                compare: None,
+                vector: Some(vector),
+                free_running: false,
+            };
+        });
+
+        MockMonotonic { _0: () }
+    }
+
+    /// Turns the monotonic into a free-running timer whose clock already reads `ticks`
+    ///
+    /// `reset` doesn't move the clock of a free-running timer back to zero.
+    pub fn free_running(self, ticks: u64) -> Self {
+        interrupt::free(|_| unsafe {
+            let state = STATE.get_mut_unchecked();
+            state.now = ticks;
+            state.free_running = true;
+        });
+
+        self
+    }
+
+    /// Moves the virtual clock forward by `duration`
+    ///
+    /// Every compare match that is reached on the way fires the bound timer queue handler with
+    /// the clock set to the instant of that match, so expired tasks are released in order.
+    pub fn advance<D>(duration: D)
+    where
+        D: Duration + FixedPoint,
+        D::T: Into<u64>,
+    {
+        let target = Instant::<Self>::new(Self::ticks()) + duration;
+
+        Self::advance_ticks(target.duration_since_epoch().integer() - Self::ticks());
+    }
+
+    /// Moves the virtual clock forward by `ticks` ticks
+    pub fn advance_ticks(ticks: u64) {
+        let target = Self::ticks() + ticks;
+
+        loop {
+            let fired = interrupt::free(|_| unsafe {
+                let state = STATE.get_mut_unchecked();
+
+                match state.compare {
+                    Some(compare) if compare <= target => {
+                        state.now = state.now.max(compare);
+                        state.compare = None;
+                        state.vector
+                    }
+                    _ => {
+                        state.now = target;
+                        None
+                    }
+                }
+            });
+
+            if let Some(vector) = fired {
+                vector.pend();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Current value of the virtual clock, in ticks
+    pub fn ticks() -> u64 {
+        interrupt::free(|_| unsafe { STATE.get_unchecked().now })
+    }
+}
+
+impl<const FREQ: u32> Clock for MockMonotonic<FREQ> {
+    type T = u64;
+
+    const SCALING_FACTOR: Fraction = Fraction::new(1, FREQ);
+
+    fn try_now(&self) -> Result<Instant<Self>, crate::time::clock::Error> {
+        Ok(Instant::new(Self::ticks()))
+    }
+}
+
+impl<const FREQ: u32> Monotonic for MockMonotonic<FREQ> {
+    unsafe fn reset(&mut self) {
+        interrupt::free(|_| {
+            let state = STATE.get_mut_unchecked();
+            if !state.free_running {
+                state.now = 0;
+            }
+            state.compare = None;
+        });
+    }
+
+    fn set_compare(&mut self, instant: &Instant<Self>) {
+        let compare = instant.duration_since_epoch().integer();
+
+        interrupt::free(|_| unsafe { STATE.get_mut_unchecked().compare = Some(compare) });
+    }
+
+    fn clear_compare_flag(&mut self) {}
+}
@@ -1,10 +1,23 @@
+#[cfg(feature = "tq-heap")]
+pub use crate::indexed_heap::{Key, Node};
+#[cfg(not(feature = "tq-heap"))]
+pub use crate::linked_list::{Key, Node};
 use crate::{
-    linked_list::{ArrayLength, LinkedList, Min, Node},
+    linked_list::{ArrayLength, Min},
     time::{Clock, Instant},
     Monotonic,
 };
 use core::cmp::Ordering;
 
+/// The queue backing the timer queues
+///
+/// A sorted linked list by default: O(n) insertion but cheap to pop. With the `tq-heap` feature
+/// an indexed binary heap, which bounds every operation to O(log n).
+#[cfg(not(feature = "tq-heap"))]
+pub type Queue<T, N> = crate::linked_list::LinkedList<T, Min, N>;
+#[cfg(feature = "tq-heap")]
+pub type Queue<T, N> = crate::indexed_heap::IndexedHeap<T, Min, N>;
+
 #[inline(always)]
 fn unwrapper<T, E>(val: Result<T, E>) -> T {
     if let Ok(v) = val {
@@ -14,7 +27,7 @@ fn unwrapper<T, E>(val: Result<T, E>) -> T {
     }
 }
 
-pub struct TimerQueue<Mono, Task, N>(pub LinkedList<NotReady<Mono, Task>, Min, N>)
+pub struct TimerQueue<Mono, Task, N>(pub Queue<NotReady<Mono, Task>, N>)
 where
     Mono: Monotonic,
     N: ArrayLength<Node<NotReady<Mono, Task>>>,
@@ -27,7 +40,7 @@ where
     Task: Copy,
 {
     pub fn new() -> Self {
-        TimerQueue(LinkedList::new())
+        TimerQueue(Queue::new())
     }
 
     /// # Safety
@@ -43,7 +56,8 @@ where
         enable_interrupt: F1,
         pend_handler: F2,
         mono: Option<&mut Mono>,
-    ) where
+    ) -> Key
+    where
         F1: FnOnce(),
         F2: FnOnce(),
     {
@@ -66,7 +80,7 @@ where
             pend_handler();
         }
 
-        self.0.push_unchecked(nr);
+        self.0.push_unchecked(nr)
     }
 
     /// Check if the timer queue is empty.
@@ -75,51 +89,67 @@ where
         self.0.is_empty()
     }
 
-    /// Cancel the marker value
-    pub fn cancel_marker(&mut self, marker: u32) -> Option<(Task, u8)> {
-        if let Some(val) = self.0.find_mut(|nr| nr.marker == marker) {
-            let nr = val.pop();
-
-            Some((nr.task, nr.index))
-        } else {
-            None
-        }
+    /// Cancel the task identified by `key`
+    ///
+    /// Complexity is O(1).
+    pub fn cancel(&mut self, key: Key) -> Option<(Task, u8)> {
+        self.0.remove(key).map(|nr| (nr.task, nr.index))
     }
 
-    /// Update the instant at an marker value to a new instant
-    pub fn update_marker<F: FnOnce()>(
+    /// Move the task identified by `key` to a new instant
+    ///
+    /// The key stays valid. On success the index of the task is returned.
+    ///
+    /// Complexity is O(n), the entry being re-sorted; O(log n) with the `tq-heap` feature.
+    pub fn update<F: FnOnce()>(
         &mut self,
-        marker: u32,
-        new_marker: u32,
+        key: Key,
         instant: Instant<Mono>,
         pend_handler: F,
-    ) -> Result<(), ()> {
-        if let Some(mut val) = self.0.find_mut(|nr| nr.marker == marker) {
-            val.instant = instant;
-            val.marker = new_marker;
+    ) -> Result<u8, ()> {
+        let mut index = 0;
 
+        if self.0.update(key, |nr| {
+            nr.instant = instant;
+            index = nr.index;
+        }) {
             // On update pend the handler to reconfigure the next compare match
             pend_handler();
 
-            Ok(())
+            Ok(index)
         } else {
             Err(())
         }
     }
 
-    /// Dequeue a task from the TimerQueue
-    pub fn dequeue<F>(&mut self, disable_interrupt: F, mono: &mut Mono) -> Option<(Task, u8)>
+    /// Move every entry with `f`, e.g. onto a new time base
+    ///
+    /// Keys to the entries stay valid.
+    ///
+    /// # Safety
+    ///
+    /// `f` must not change the relative order of the entries, e.g. it must shift all instants by
+    /// the same amount
+    pub unsafe fn rebase<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut NotReady<Mono, Task>),
+    {
+        self.0.for_each_mut(f)
+    }
+
+    /// Dequeue a task from the TimerQueue, along with the key it was enqueued under
+    pub fn dequeue<F>(&mut self, disable_interrupt: F, mono: &mut Mono) -> Option<(Task, u8, Key)>
     where
         F: FnOnce(),
     {
         mono.clear_compare_flag();
 
-        if let Some(instant) = self.0.peek().map(|p| p.instant) {
+        if let Some((instant, key)) = self.0.peek().map(|p| p.instant).zip(self.0.peek_key()) {
             if instant <= unwrapper(Clock::try_now(mono)) {
                 // task became ready
                 let nr = unsafe { self.0.pop_unchecked() };
 
-                Some((nr.task, nr.index))
+                Some((nr.task, nr.index, key))
             } else {
                 // Set compare
                 mono.set_compare(&instant);
@@ -131,7 +161,7 @@ where
                 if instant <= unwrapper(Clock::try_now(mono)) {
                     let nr = unsafe { self.0.pop_unchecked() };
 
-                    Some((nr.task, nr.index))
+                    Some((nr.task, nr.index, key))
                 } else {
                     None
                 }
@@ -156,7 +186,6 @@ where
     pub index: u8,
     pub instant: Instant<Mono>,
     pub task: Task,
-    pub marker: u32,
 }
 
 impl<Mono, Task> Eq for NotReady<Mono, Task>
@@ -0,0 +1,11 @@
+//! Diagnostics that are specific to ARMv6-M; run with `RUSTFLAGS="--cfg armv6m"`, the cfg that
+//! `build.rs` sets for ARMv6-M targets
+#![cfg(armv6m)]
+
+use trybuild::TestCases;
+
+#[test]
+fn ui() {
+    let t = TestCases::new();
+    t.compile_fail("ui/armv6m/*.rs");
+}
@@ -0,0 +1,36 @@
+//! Items shared by the host tests; not every test uses all of them
+
+#![allow(dead_code, unused_imports, unused_macros)]
+
+/// Minimal stand-in for a device crate
+pub mod pac {
+    pub const NVIC_PRIO_BITS: u8 = 3;
+
+    #[derive(Clone, Copy)]
+    #[repr(u16)]
+    pub enum Interrupt {
+        GPIOA = 0,
+        GPIOB = 1,
+        GPIOC = 2,
+        UART0 = 5,
+        SSI0 = 7,
+    }
+
+    unsafe impl cortex_m::interrupt::InterruptNumber for Interrupt {
+        fn number(self) -> u16 {
+            self as u16
+        }
+    }
+
+    pub use Interrupt as interrupt;
+
+    /// Hands the interrupts that may serve as dispatchers to `$callback`; `dispatchers = auto`
+    /// skips the ones bound by hardware tasks and monotonics
+    macro_rules! rtic_spare_interrupts {
+        ($callback:path, $($input:tt)*) => {
+            $callback! { [GPIOA, GPIOB, GPIOC, UART0, SSI0] $($input)* }
+        };
+    }
+
+    pub(crate) use rtic_spare_interrupts;
+}
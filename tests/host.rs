@@ -0,0 +1,164 @@
+//! Runs an RTIC application on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+static OVERRUNS: Mutex<Vec<(&str, u64)>> = Mutex::new(Vec::new());
+static MESSAGES: Mutex<Vec<(&str, u32)>> = Mutex::new(Vec::new());
+
+fn trace(event: &'static str) {
+    TRACE.lock().unwrap().push(event);
+}
+
+fn on_overrun(task: &'static str, cycles: u64) {
+    OVERRUNS.lock().unwrap().push((task, cycles));
+}
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0], budget_overrun = crate::on_overrun)]
+mod app {
+    use super::{pac::Interrupt, trace, MESSAGES};
+    use rtic::SpawnError;
+
+    #[resources]
+    struct Resources {
+        #[init(0)]
+        shared: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        rtic::pend(Interrupt::GPIOA);
+        // Masked, so it must not keep the default `idle` loop from returning
+        rtic::pend(Interrupt::UART0);
+
+        for x in 1..=2 {
+            oldest::spawn(x).unwrap();
+            latest::spawn(x).unwrap();
+            strict::spawn(x).unwrap();
+        }
+
+        // Nothing is dispatched before `init` returns, so all the slots are taken
+        oldest::spawn(3).unwrap();
+        latest::spawn(3).unwrap();
+        assert_eq!(strict::spawn(3).err(), Some(SpawnError::Full(3)));
+
+        // Coalesced until `wake` starts running
+        assert_eq!(wake::spawn_unique(), Ok(true));
+        assert_eq!(wake::spawn_unique(), Ok(false));
+
+        trace("init");
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[task(binds = GPIOA, resources = [shared])]
+    fn gpioa(mut c: gpioa::Context) {
+        trace("A");
+
+        c.resources.shared.lock(|shared| {
+            *shared += 1;
+
+            // GPIOB will *not* run right now due to the critical section
+            rtic::pend(Interrupt::GPIOB);
+
+            trace("B");
+
+            // GPIOC does not contend for `shared` so it's allowed to run now
+            rtic::pend(Interrupt::GPIOC);
+        });
+
+        trace("E");
+
+        foo::spawn(1).unwrap();
+    }
+
+    #[task(binds = GPIOB, priority = 2, resources = [shared])]
+    fn gpiob(mut c: gpiob::Context) {
+        c.resources.shared.lock(|shared| *shared += 1);
+
+        trace("D");
+    }
+
+    #[task(binds = GPIOC, priority = 3, budget_cycles = 100)]
+    fn gpioc(_: gpioc::Context) {
+        rtic::host::advance_cycles(150);
+
+        trace("C");
+    }
+
+    #[task(resources = [shared], budget_cycles = 1_000)]
+    fn foo(mut c: foo::Context, x: u32) {
+        rtic::host::advance_cycles(10);
+
+        let shared = c.resources.shared.lock(|shared| *shared);
+
+        assert_eq!(shared, 2);
+        assert_eq!(x, 1);
+
+        // The first `wake` has run by now
+        assert_eq!(wake::spawn_unique(), Ok(true));
+
+        trace("foo");
+    }
+
+    #[task(capacity = 2, overflow = drop_oldest)]
+    fn oldest(_: oldest::Context, x: u32) {
+        MESSAGES.lock().unwrap().push(("oldest", x));
+    }
+
+    #[task(capacity = 2, overflow = replace_latest)]
+    fn latest(_: latest::Context, x: u32) {
+        MESSAGES.lock().unwrap().push(("latest", x));
+    }
+
+    #[task(capacity = 2, overflow = error)]
+    fn strict(_: strict::Context, x: u32) {
+        MESSAGES.lock().unwrap().push(("strict", x));
+    }
+
+    #[task]
+    fn wake(_: wake::Context) {
+        MESSAGES.lock().unwrap().push(("wake", 0));
+    }
+}
+
+#[test]
+fn preemption() {
+    app::run();
+
+    assert_eq!(
+        *TRACE.lock().unwrap(),
+        ["init", "A", "B", "C", "D", "E", "foo"]
+    );
+
+    // `gpioc` preempted `gpioa`, which has no budget, so its cycles are only counted once
+    assert_eq!(
+        app::gpioc::execution_time(),
+        rtic::ExecutionTime {
+            max: 150,
+            overruns: 1,
+            runs: 1
+        }
+    );
+    assert_eq!(app::foo::execution_time().max, 10);
+    assert_eq!(app::foo::execution_time().overruns, 0);
+    assert_eq!(*OVERRUNS.lock().unwrap(), [("gpioc", 150)]);
+
+    assert_eq!(
+        *MESSAGES.lock().unwrap(),
+        [
+            ("latest", 1),
+            ("strict", 1),
+            ("oldest", 2),
+            ("latest", 3),
+            ("strict", 2),
+            ("oldest", 3),
+            ("wake", 0),
+            ("wake", 0),
+        ]
+    );
+}
@@ -0,0 +1,93 @@
+//! Drives `async` software tasks and delays on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<(u32, u64)>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::{
+        testing::MockMonotonic,
+        time::{duration::Milliseconds, Instant},
+        SpawnError,
+    };
+
+    #[monotonic(binds = UART0, default = true)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[resources]
+    struct Resources {
+        #[init(0)]
+        iterations: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        blink::spawn(1).unwrap();
+        blink::spawn(2).unwrap();
+        assert_eq!(blink::spawn(3).err(), Some(SpawnError::Full(3)));
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle(resources = [iterations])]
+    fn idle(mut c: idle::Context) -> ! {
+        // Both instances have run up to their first delay
+        assert_eq!(*TRACE.lock().unwrap(), [(1, 0), (2, 0)]);
+        assert_eq!(monotonics::next_deadline(), Some(Instant::new(10)));
+
+        // Instances hold on to their slot while they're waiting
+        MyMono::advance(Milliseconds(10_u32));
+        assert_eq!(blink::spawn(3).err(), Some(SpawnError::Full(3)));
+
+        // The first instance completes and frees its slot
+        MyMono::advance(Milliseconds(10_u32));
+        blink::spawn(3).unwrap();
+
+        MyMono::advance(Milliseconds(30_u32));
+
+        assert_eq!(c.resources.iterations.lock(|iterations| *iterations), 6);
+        assert_eq!(monotonics::next_deadline(), Some(Instant::new(80)));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 2, resources = [iterations])]
+    async fn blink(mut cx: blink::Context, id: u32) {
+        for _ in 0..2 {
+            cx.resources.iterations.lock(|iterations| *iterations += 1);
+            TRACE.lock().unwrap().push((id, MyMono::ticks()));
+
+            monotonics::delay(Milliseconds(10 * id)).await;
+        }
+
+        TRACE.lock().unwrap().push((10 + id, MyMono::ticks()));
+    }
+}
+
+#[test]
+fn async_tasks() {
+    app::run();
+
+    assert_eq!(
+        *TRACE.lock().unwrap(),
+        [
+            (1, 0),
+            (2, 0),
+            (1, 10),
+            (11, 20),
+            (2, 20),
+            (3, 20),
+            (12, 40),
+            (3, 50)
+        ]
+    );
+}
@@ -0,0 +1,65 @@
+//! Dispatchers picked from the spare interrupts of the device on the host backend
+//! (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = auto)]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::host::NVIC;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        low::spawn().unwrap();
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        // `GPIOA` is bound by `button`, so the two levels get the next spare interrupts, the
+        // highest level first
+        assert!(NVIC::is_enabled(Interrupt::GPIOB));
+        assert!(NVIC::is_enabled(Interrupt::GPIOC));
+        assert!(NVIC::get_priority(Interrupt::GPIOB) < NVIC::get_priority(Interrupt::GPIOC));
+        assert!(!NVIC::is_enabled(Interrupt::UART0));
+        assert!(!NVIC::is_enabled(Interrupt::SSI0));
+
+        rtic::pend(Interrupt::GPIOA);
+
+        assert_eq!(*TRACE.lock().unwrap(), ["low", "high", "low end", "button"]);
+
+        rtic::host::exit()
+    }
+
+    #[task(binds = GPIOA)]
+    fn button(_: button::Context) {
+        TRACE.lock().unwrap().push("button");
+    }
+
+    #[task]
+    fn low(_: low::Context) {
+        TRACE.lock().unwrap().push("low");
+
+        // Preempts right away, it has a dispatcher of its own
+        high::spawn().unwrap();
+
+        TRACE.lock().unwrap().push("low end");
+    }
+
+    #[task(priority = 2)]
+    fn high(_: high::Context) {
+        TRACE.lock().unwrap().push("high");
+    }
+}
+
+#[test]
+fn auto() {
+    app::run();
+}
@@ -0,0 +1,59 @@
+//! Channels between tasks on the host backend (`--features host`)
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::pac::Interrupt;
+
+    #[channels]
+    struct Channels {
+        #[channel(capacity = 2, spawn = consumer)]
+        samples: u32,
+        #[channel(capacity = 4)]
+        results: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle(receivers = [results])]
+    fn idle(mut c: idle::Context) -> ! {
+        assert!(c.receivers.results.is_empty());
+
+        rtic::pend(Interrupt::UART0);
+
+        assert_eq!(c.receivers.results.len(), 2);
+        assert_eq!(c.receivers.results.recv(), Some(10));
+        assert_eq!(c.receivers.results.recv(), Some(20));
+        assert_eq!(c.receivers.results.recv(), None);
+
+        rtic::host::exit()
+    }
+
+    #[task(binds = UART0, priority = 2, senders = [samples])]
+    fn uart0(mut c: uart0::Context) {
+        c.senders.samples.send(1).unwrap();
+        c.senders.samples.send(2).unwrap();
+
+        // `consumer` only runs once this task returns
+        assert!(c.senders.samples.is_full());
+        assert_eq!(c.senders.samples.send(3), Err(3));
+    }
+
+    #[task(receivers = [samples], senders = [results])]
+    fn consumer(mut c: consumer::Context) {
+        while let Some(sample) = c.receivers.samples.recv() {
+            c.senders.results.send(sample * 10).unwrap();
+        }
+    }
+}
+
+#[test]
+fn channels() {
+    app::run();
+}
@@ -0,0 +1,61 @@
+//! Earliest-deadline-first dispatching on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [#[edf] SSI0])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::{
+        testing::MockMonotonic,
+        time::{duration::Milliseconds, Instant},
+    };
+
+    #[monotonic(binds = UART0, default = true)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        job::spawn_with_deadline(Instant::new(30), 3).unwrap();
+        job::spawn_with_deadline(Instant::new(10), 1).unwrap();
+        // The monotonic is not ready yet, so this gets the earliest deadline
+        job::spawn(0).unwrap();
+        job::spawn_with_deadline(Instant::new(20), 2).unwrap();
+        // Equal deadlines are dispatched in spawn order
+        job::spawn_with_deadline(Instant::new(10), 5).unwrap();
+        job::spawn_with_deadline(Instant::new(10), 6).unwrap();
+
+        job::spawn_at(Instant::new(15), 4).unwrap();
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        assert_eq!(*TRACE.lock().unwrap(), [0, 1, 5, 6, 2, 3]);
+
+        MyMono::advance(Milliseconds(20_u32));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 8)]
+    fn job(_: job::Context, x: u32) {
+        TRACE.lock().unwrap().push(x);
+    }
+}
+
+#[test]
+fn edf() {
+    app::run();
+
+    assert_eq!(*TRACE.lock().unwrap(), [0, 1, 5, 6, 2, 3, 4]);
+}
@@ -0,0 +1,58 @@
+//! Software tasks dispatched from the PendSV and SVCall exceptions on the host backend
+//! (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [PendSV, SVCall])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::host::{SystemHandler, NVIC, SCB};
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        low::spawn().unwrap();
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        // The highest level is dispatched from the first exception
+        assert!(
+            SCB::get_priority(SystemHandler::PendSV) < SCB::get_priority(SystemHandler::SVCall)
+        );
+        assert!(!NVIC::is_enabled(Interrupt::SSI0));
+
+        assert_eq!(*TRACE.lock().unwrap(), ["low", "high", "low end", "high"]);
+
+        rtic::host::exit()
+    }
+
+    #[task]
+    fn low(_: low::Context) {
+        TRACE.lock().unwrap().push("low");
+
+        // Pends PendSV, which preempts SVCall
+        high::spawn().unwrap();
+
+        TRACE.lock().unwrap().push("low end");
+
+        high::spawn().unwrap();
+    }
+
+    #[task(priority = 2, capacity = 2)]
+    fn high(_: high::Context) {
+        TRACE.lock().unwrap().push("high");
+    }
+}
+
+#[test]
+fn exceptions() {
+    app::run();
+}
@@ -0,0 +1,74 @@
+//! Tasks scheduled from `init` on a monotonic that `reset` can't zero, and the deadlines of the
+//! tasks spawned from `init` on an EDF level (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<(u32, u64)>> = Mutex::new(Vec::new());
+static JOBS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0, #[edf] GPIOA])]
+mod app {
+    use super::{pac::Interrupt, JOBS, TRACE};
+    use rtic::{
+        testing::MockMonotonic,
+        time::{duration::Milliseconds, Instant},
+    };
+
+    #[monotonic(binds = UART0, default = true)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        foo::spawn_after(Milliseconds(20_u32), 2).unwrap();
+        foo::spawn_after(Milliseconds(10_u32), 1).unwrap();
+        // Instants are relative to the end of `init` as well
+        foo::spawn_at(Instant::new(5), 0).unwrap();
+
+        // So are EDF deadlines
+        job::spawn_with_deadline(Instant::new(10), 1).unwrap();
+        job::spawn(0).unwrap();
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0).free_running(1_000)),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        // Nothing is due at the instants `init` asked for
+        assert!(TRACE.lock().unwrap().is_empty());
+
+        MyMono::advance(Milliseconds(30_u32));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 4)]
+    fn foo(_: foo::Context, x: u32) {
+        TRACE.lock().unwrap().push((x, MyMono::ticks()));
+    }
+
+    #[task(priority = 2, capacity = 4)]
+    fn job(_: job::Context, x: u32) {
+        JOBS.lock().unwrap().push(x);
+
+        // Both deadlines are earlier than the 1_010 ms of the job from `init`
+        if x == 0 {
+            job::spawn(2).unwrap();
+            job::spawn_with_deadline(monotonics::MyMono::now() + Milliseconds(5_u32), 3).unwrap();
+        }
+    }
+}
+
+#[test]
+fn free_running() {
+    app::run();
+
+    assert_eq!(*TRACE.lock().unwrap(), [(0, 1005), (1, 1010), (2, 1020)]);
+    assert_eq!(*JOBS.lock().unwrap(), [0, 2, 3, 1]);
+}
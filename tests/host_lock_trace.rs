@@ -0,0 +1,102 @@
+//! Critical section times of the resource locks on the host backend
+//! (`--features host,lock-trace`)
+
+use std::sync::Mutex;
+
+static LOCKS: Mutex<Vec<(u16, u8, u32)>> = Mutex::new(Vec::new());
+
+pub struct Trace;
+
+impl rtic::Tracer for Trace {
+    fn lock(resource: u16, ceiling: u8, cycles: u32) {
+        LOCKS.lock().unwrap().push((resource, ceiling, cycles));
+    }
+}
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, tracer = crate::Trace)]
+mod app {
+    use super::{pac::Interrupt, LOCKS};
+    use rtic::{host::advance_cycles, LockStats};
+
+    #[resources]
+    struct Resources {
+        #[init(0)]
+        shared: u32,
+        #[init(0)]
+        other: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle(resources = [shared, other])]
+    fn idle(mut c: idle::Context) -> ! {
+        c.resources.shared.lock(|shared| {
+            *shared += 1;
+            advance_cycles(100);
+        });
+        c.resources.shared.lock(|_| advance_cycles(40));
+
+        let shared = &mut c.resources.shared;
+        c.resources.other.lock(|_| {
+            // Already at the ceiling of `shared`, so this one isn't timed
+            shared.lock(|_| advance_cycles(500));
+            advance_cycles(10);
+        });
+
+        // `mid` runs at the ceiling of `shared` too
+        rtic::pend(Interrupt::GPIOA);
+
+        assert_eq!(
+            *LOCKS.lock().unwrap(),
+            [(0, 2, 100), (0, 2, 40), (1, 3, 510)]
+        );
+        assert_eq!(RESOURCE_NAMES, ["shared", "other"]);
+        assert_eq!(
+            rtic::lock_stats().collect::<Vec<_>>(),
+            [
+                LockStats {
+                    resource: "shared",
+                    ceiling: 2,
+                    locks: 2,
+                    max_cycles: 100,
+                },
+                LockStats {
+                    resource: "other",
+                    ceiling: 3,
+                    locks: 1,
+                    max_cycles: 510,
+                },
+            ]
+        );
+
+        rtic::reset_lock_stats();
+        assert!(rtic::lock_stats().all(|stats| stats.locks == 0 && stats.max_cycles == 0));
+
+        rtic::host::exit()
+    }
+
+    #[task(binds = GPIOA, priority = 2, resources = [shared])]
+    fn mid(mut c: mid::Context) {
+        c.resources.shared.lock(|shared| {
+            *shared += 1;
+            advance_cycles(1_000);
+        });
+    }
+
+    #[task(binds = GPIOB, priority = 3, resources = [other])]
+    fn top(mut c: top::Context) {
+        c.resources.other.lock(|other| *other += 1);
+    }
+}
+
+#[test]
+fn lock_trace() {
+    app::run();
+}
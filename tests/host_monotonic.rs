@@ -0,0 +1,169 @@
+//! Drives the timer queue with `MockMonotonic` on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<(u32, u64)>> = Mutex::new(Vec::new());
+static MISSES: Mutex<Vec<(&str, u64)>> = Mutex::new(Vec::new());
+
+fn on_miss(task: &'static str, lateness: u64) {
+    MISSES.lock().unwrap().push((task, lateness));
+}
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0], deadline_miss = crate::on_miss)]
+mod app {
+    use super::{pac::Interrupt, MISSES, TRACE};
+    use rtic::{
+        testing::MockMonotonic,
+        time::{duration::Milliseconds, Instant},
+        SpawnError,
+    };
+
+    // Above `foo` so that releases can happen while `idle` holds `gate`
+    #[monotonic(binds = UART0, default = true, priority = 2)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[resources]
+    struct Resources {
+        #[init(())]
+        gate: (),
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        foo::spawn_after(Milliseconds(30_u32), 3).unwrap();
+        foo::spawn_after(Milliseconds(10_u32), 1).unwrap();
+
+        let handle = foo::spawn_after(Milliseconds(20_u32), 2).unwrap();
+        // The monotonic is not available until `init` returns, so use an absolute instant
+        let handle = match handle.reschedule_after(Milliseconds(40_u32)) {
+            Err(SpawnError::MonotonicNotReady(handle)) => handle,
+            _ => panic!("the monotonic is not ready yet"),
+        };
+        handle.reschedule_at(Instant::new(40)).unwrap();
+
+        let handle = foo::spawn_after(Milliseconds(25_u32), 4).unwrap();
+        assert_eq!(handle.cancel().ok(), Some(4));
+
+        // Not dispatched before `init` returns
+        let handle = foo::spawn(5).unwrap();
+        assert_eq!(handle.cancel().ok(), Some(5));
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle(resources = [gate])]
+    fn idle(mut c: idle::Context) -> ! {
+        MyMono::advance(Milliseconds(15_u32));
+
+        // Only the first task has expired so far
+        assert_eq!(TRACE.lock().unwrap().len(), 1);
+        assert_eq!(monotonics::next_deadline(), Some(Instant::new(30)));
+
+        // Sleep until the next deadline, the clock jump stands in for the compensation
+        let deadline = monotonics::sleep(|_, deadline| {
+            let deadline = deadline.unwrap();
+            MyMono::advance_ticks(deadline.duration_since_epoch().integer() - MyMono::ticks());
+            deadline
+        });
+
+        assert_eq!(deadline, Instant::new(30));
+        assert_eq!(TRACE.lock().unwrap().len(), 2);
+
+        MyMono::advance(Milliseconds(100_u32));
+
+        // Released on time, then 10 ms late
+        bar::spawn_after(Milliseconds(0_u32)).unwrap();
+        bar::spawn_at(Instant::new(120)).unwrap();
+
+        assert_eq!(
+            bar::lateness(),
+            rtic::Lateness {
+                max: 10,
+                misses: 1,
+                releases: 2
+            }
+        );
+        assert_eq!(*MISSES.lock().unwrap(), [("bar", 10)]);
+
+        // Released right away, too late to cancel
+        let handle = bar::spawn_after(Milliseconds(0_u32)).unwrap();
+        assert_eq!(handle.cancel(), Err(SpawnError::AlreadyDispatched));
+
+        // Debouncing pushes the pending instance back and replaces its message
+        let first = foo::spawn_or_reschedule_after(Milliseconds(20_u32), 10).unwrap();
+        MyMono::advance(Milliseconds(10_u32));
+        let second = foo::spawn_or_reschedule_after(Milliseconds(20_u32), 11).unwrap();
+        MyMono::advance(Milliseconds(15_u32));
+        assert_eq!(TRACE.lock().unwrap().len(), 3);
+
+        // Both handles refer to the same instance, until one of them cancels it
+        assert_eq!(first.cancel(), Ok(11));
+
+        // Nothing is pending anymore, so a new instance is spawned, and again once it has run
+        foo::spawn_or_reschedule_after(Milliseconds(5_u32), 12).unwrap();
+        // Still stale, although a newer instance is now the one being rescheduled
+        assert_eq!(second.cancel(), Err(SpawnError::Stale));
+        MyMono::advance(Milliseconds(5_u32));
+        foo::spawn_or_reschedule_after(Milliseconds(5_u32), 13).unwrap();
+        MyMono::advance(Milliseconds(5_u32));
+
+        // Instances waiting in the ready queue can still be cancelled
+        c.resources.gate.lock(|_| {
+            let released = foo::spawn_after(Milliseconds(5_u32), 14).unwrap();
+            let ready = foo::spawn(15).unwrap();
+            foo::spawn(16).unwrap();
+            MyMono::advance(Milliseconds(5_u32));
+
+            assert_eq!(released.cancel(), Ok(14));
+            assert_eq!(ready.cancel(), Ok(15));
+        });
+
+        // Dispatched once `gate` is released
+        let ready = foo::spawn(17).unwrap();
+        assert_eq!(ready.cancel(), Err(SpawnError::AlreadyDispatched));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 4, resources = [gate])]
+    fn foo(_: foo::Context, x: u32) {
+        TRACE.lock().unwrap().push((x, MyMono::ticks()));
+    }
+
+    #[task(max_latency = Milliseconds(5_u32), budget = Milliseconds(1_u32))]
+    fn bar(_: bar::Context) {}
+}
+
+#[test]
+fn timer_queue() {
+    app::run();
+
+    assert_eq!(
+        *TRACE.lock().unwrap(),
+        [
+            (1, 10),
+            (3, 30),
+            (2, 40),
+            (12, 160),
+            (13, 165),
+            (16, 170),
+            (17, 170)
+        ]
+    );
+    // Time only moves when the tests advance it, never while `bar` runs
+    assert_eq!(
+        app::bar::execution_time(),
+        rtic::ExecutionTime {
+            max: 0,
+            overruns: 0,
+            runs: 3
+        }
+    );
+}
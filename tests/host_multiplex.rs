@@ -0,0 +1,68 @@
+//! One dispatcher serving several priority levels on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [#[multiplex] SSI0])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        low::spawn().unwrap();
+        mid::spawn().unwrap();
+        high::spawn(true).unwrap();
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        assert_eq!(
+            *TRACE.lock().unwrap(),
+            ["high", "mid", "uart0", "low", "low end", "high"]
+        );
+
+        rtic::host::exit()
+    }
+
+    #[task(binds = UART0, priority = 2)]
+    fn uart0(_: uart0::Context) {
+        TRACE.lock().unwrap().push("uart0");
+    }
+
+    #[task]
+    fn low(_: low::Context) {
+        TRACE.lock().unwrap().push("low");
+
+        // Multiplexed levels don't preempt each other
+        high::spawn(false).unwrap();
+
+        TRACE.lock().unwrap().push("low end");
+    }
+
+    #[task(priority = 2)]
+    fn mid(_: mid::Context) {
+        TRACE.lock().unwrap().push("mid");
+    }
+
+    #[task(priority = 3)]
+    fn high(_: high::Context, pend: bool) {
+        TRACE.lock().unwrap().push("high");
+
+        // Runs once the dispatcher has dropped below priority 2
+        if pend {
+            rtic::pend(Interrupt::UART0);
+        }
+    }
+}
+
+#[test]
+fn multiplex() {
+    app::run();
+}
@@ -0,0 +1,81 @@
+//! Periodic software tasks on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::{testing::MockMonotonic, time::duration::Milliseconds};
+
+    // Above `tick` so that releases can happen while `idle` holds `gate`
+    #[monotonic(binds = UART0, default = true, priority = 2)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[resources]
+    struct Resources {
+        #[init(())]
+        gate: (),
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        // Periodic tasks are already running when `init` starts
+        assert_eq!(tick::start(), Err(()));
+
+        // Restarted here, the offset still counts from when `init` returns
+        tick::stop();
+        tick::start().unwrap();
+
+        // Never released
+        off::stop();
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle(resources = [gate])]
+    fn idle(mut c: idle::Context) -> ! {
+        // Released at 5 ms but only dispatched at 12 ms; the next release is still at 15 ms
+        c.resources
+            .gate
+            .lock(|_| MyMono::advance(Milliseconds(12_u32)));
+        MyMono::advance(Milliseconds(13_u32));
+        assert_eq!(*TRACE.lock().unwrap(), [12, 15, 25]);
+
+        assert_eq!(tick::start(), Err(()));
+        tick::stop();
+        MyMono::advance(Milliseconds(30_u32));
+        assert_eq!(TRACE.lock().unwrap().len(), 3);
+
+        // The first release after a `start` happens `offset` from now
+        tick::start().unwrap();
+        MyMono::advance(Milliseconds(20_u32));
+
+        rtic::host::exit()
+    }
+
+    #[task(resources = [gate], period = Milliseconds(10_u32), offset = Milliseconds(5_u32))]
+    fn tick(_: tick::Context) {
+        TRACE.lock().unwrap().push(MyMono::ticks());
+    }
+
+    #[task(period = Milliseconds(1_u32))]
+    fn off(_: off::Context) {
+        unreachable!("stopped in `init`");
+    }
+}
+
+#[test]
+fn periodic() {
+    app::run();
+
+    assert_eq!(*TRACE.lock().unwrap(), [12, 15, 25, 60, 70]);
+}
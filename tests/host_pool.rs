@@ -0,0 +1,75 @@
+//! Memory pools on the host backend (`--features host`)
+
+mod common;
+
+pub use common::pac;
+
+#[derive(Debug, PartialEq)]
+pub struct Frame {
+    pub data: [u8; 256],
+}
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+#[pool(Frame, 2)]
+mod app {
+    use super::Frame;
+
+    #[resources]
+    struct Resources {
+        #[init(0)]
+        received: u32,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        assert_eq!(pools::available::<Frame>(), 2);
+
+        rx::spawn_boxed(Frame { data: [1; 256] }).unwrap();
+
+        let boxed = pools::alloc(Frame { data: [2; 256] }).unwrap();
+        assert_eq!(pools::available::<Frame>(), 0);
+
+        // Exhaustion is reported through the spawn error, with the value handed back
+        assert_eq!(
+            rx::spawn_boxed(Frame { data: [3; 256] }).err(),
+            Some(rtic::SpawnError::PoolExhausted(Frame { data: [3; 256] }))
+        );
+
+        rx::spawn(boxed).unwrap();
+
+        // Boxes from the heap don't get a `spawn_boxed`
+        heap::spawn(std::boxed::Box::new(Frame { data: [4; 256] })).unwrap();
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle(resources = [received])]
+    fn idle(mut c: idle::Context) -> ! {
+        c.resources
+            .received
+            .lock(|received| assert_eq!(*received, 2));
+
+        // The blocks went back to the pool when the tasks dropped them
+        assert_eq!(pools::available::<Frame>(), 2);
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 2, resources = [received])]
+    fn rx(mut c: rx::Context, frame: rtic::Box<Frame>) {
+        c.resources.received.lock(|received| {
+            *received += 1;
+            assert_eq!(frame.data[0], *received as u8);
+        });
+    }
+
+    #[task]
+    fn heap(_: heap::Context, frame: std::boxed::Box<Frame>) {
+        assert_eq!(frame.data[0], 4);
+    }
+}
+
+#[test]
+fn pool() {
+    app::run();
+}
@@ -0,0 +1,87 @@
+//! In-place spawns and `by_ref` tasks on the host backend (`--features host`)
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+
+pub use common::pac;
+
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Frame {
+    pub data: [u8; 1024],
+    pub len: usize,
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        DROPS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::{Frame, DROPS};
+    use core::sync::atomic::Ordering;
+
+    #[resources]
+    struct Resources {
+        #[init(0)]
+        total: usize,
+    }
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        // A panicking `init` gives its slot back
+        assert!(
+            std::panic::catch_unwind(|| unsafe { rx::spawn_with(|_| panic!("no frame")) }).is_err()
+        );
+
+        for n in 1..=2 {
+            unsafe {
+                rx::spawn_with(|slot| {
+                    let frame = slot.as_mut_ptr();
+                    (*frame).data = [n; 1024];
+                    (*frame).len = usize::from(n) * 100;
+                })
+            }
+            .unwrap();
+        }
+
+        // Both slots are in use; `init` isn't called
+        assert!(unsafe { rx::spawn_with(|_| unreachable!()) }.is_err());
+
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[idle(resources = [total])]
+    fn idle(mut c: idle::Context) -> ! {
+        c.resources.total.lock(|total| assert_eq!(*total, 300));
+        assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+
+        rtic::host::exit()
+    }
+
+    #[task(by_ref, capacity = 2, resources = [total])]
+    fn rx(mut c: rx::Context, frame: &mut Frame) {
+        static mut RUNS: u8 = 0;
+
+        *RUNS += 1;
+        assert_eq!(*RUNS, frame.data[0]);
+        assert_eq!(usize::from(frame.data[0]) * 100, frame.len);
+
+        // The slot is reused only once the previous message was dropped
+        assert_eq!(
+            DROPS.load(Ordering::Relaxed),
+            usize::from(frame.data[0]) - 1
+        );
+        frame.data[1] = 0xff;
+
+        c.resources.total.lock(|total| *total += frame.len);
+    }
+}
+
+#[test]
+fn spawn_with() {
+    app::run();
+}
@@ -0,0 +1,80 @@
+//! Queue statistics on the host backend (`--features host,stats`)
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::pac::Interrupt;
+    use rtic::{testing::MockMonotonic, time::duration::Milliseconds, QueueId, QueueStats};
+
+    #[monotonic(binds = UART0, default = true, priority = 2)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        foo::spawn(1).unwrap();
+        foo::spawn(2).unwrap();
+        foo::spawn_after(Milliseconds(10_u32), 3).unwrap();
+        assert!(foo::spawn(4).is_err());
+        assert!(bar::spawn().is_ok());
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        MyMono::advance(Milliseconds(10_u32));
+
+        let stats = rtic::stats().collect::<Vec<_>>();
+        assert_eq!(
+            stats,
+            [
+                QueueStats {
+                    queue: QueueId::Free("foo"),
+                    capacity: 3,
+                    high_watermark: 3,
+                    failures: 1,
+                },
+                QueueStats {
+                    queue: QueueId::Free("bar"),
+                    capacity: 1,
+                    high_watermark: 1,
+                    failures: 0,
+                },
+                QueueStats {
+                    queue: QueueId::Ready(1),
+                    capacity: 4,
+                    high_watermark: 3,
+                    failures: 0,
+                },
+                QueueStats {
+                    queue: QueueId::Timer("MyMono"),
+                    capacity: 4,
+                    high_watermark: 1,
+                    failures: 0,
+                },
+            ]
+        );
+
+        rtic::reset_stats();
+        assert!(rtic::stats().all(|stats| stats.high_watermark == 0 && stats.failures == 0));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 3)]
+    fn foo(_: foo::Context, _: u32) {}
+
+    #[task]
+    fn bar(_: bar::Context) {}
+}
+
+#[test]
+fn stats() {
+    app::run();
+}
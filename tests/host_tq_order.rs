@@ -0,0 +1,58 @@
+//! Release order of timer queue entries with equal instants (`--features host`, with and without
+//! `tq-heap`)
+
+use std::sync::Mutex;
+
+static TRACE: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0])]
+mod app {
+    use super::{pac::Interrupt, TRACE};
+    use rtic::{
+        testing::MockMonotonic,
+        time::{duration::Milliseconds, Instant},
+    };
+
+    #[monotonic(binds = UART0, default = true)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        let mut handles = vec![];
+        for x in 0..9 {
+            let instant = Instant::new(if x % 3 == 0 { 20 } else { 10 });
+            handles.push(foo::spawn_at(instant, x).unwrap());
+        }
+
+        // A rescheduled entry goes behind the ones with the same instant
+        handles.remove(1).reschedule_at(Instant::new(10)).unwrap();
+
+        MyMono::advance(Milliseconds(20_u32));
+
+        rtic::host::exit()
+    }
+
+    #[task(capacity = 9)]
+    fn foo(_: foo::Context, x: u32) {
+        TRACE.lock().unwrap().push(x);
+    }
+}
+
+#[test]
+fn equal_instants() {
+    app::run();
+
+    assert_eq!(*TRACE.lock().unwrap(), [2, 4, 5, 7, 8, 1, 0, 3, 6]);
+}
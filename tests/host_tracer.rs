@@ -0,0 +1,100 @@
+//! Task tracing hooks on the host backend (`--features host`)
+
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Enter(u16),
+    Exit(u16),
+    Spawn(u16),
+}
+
+static EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+pub struct Trace;
+
+impl rtic::Tracer for Trace {
+    fn task_enter(task: u16) {
+        EVENTS.lock().unwrap().push(Event::Enter(task));
+    }
+
+    fn task_exit(task: u16) {
+        EVENTS.lock().unwrap().push(Event::Exit(task));
+    }
+
+    fn spawn(task: u16) {
+        EVENTS.lock().unwrap().push(Event::Spawn(task));
+    }
+}
+
+mod common;
+
+pub use common::pac;
+
+#[rtic::app(device = crate::pac, dispatchers = [SSI0], tracer = crate::Trace)]
+mod app {
+    use super::{pac::Interrupt, Event::*, EVENTS};
+    use rtic::{testing::MockMonotonic, time::duration::Milliseconds};
+
+    #[monotonic(binds = UART0, default = true, priority = 2)]
+    type MyMono = MockMonotonic<1_000>;
+
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        // Nothing is traced before `init` returns
+        assert!(EVENTS.lock().unwrap().is_empty());
+
+        (
+            init::LateResources {},
+            init::Monotonics(MyMono::new(Interrupt::UART0)),
+        )
+    }
+
+    #[idle]
+    fn idle(_: idle::Context) -> ! {
+        rtic::pend(Interrupt::GPIOA);
+
+        // The timer queue handler runs once to set the compare, and again to release `later`
+        later::spawn_after(Milliseconds(10_u32)).unwrap();
+        MyMono::advance(Milliseconds(10_u32));
+
+        let mono = monotonics::MyMono::ID;
+        assert_eq!(
+            *EVENTS.lock().unwrap(),
+            [
+                Enter(idle::ID),
+                Enter(button::ID),
+                Spawn(work::ID),
+                Exit(button::ID),
+                Enter(work::ID),
+                Exit(work::ID),
+                Enter(mono),
+                Exit(mono),
+                Enter(mono),
+                Spawn(later::ID),
+                Exit(mono),
+                Enter(later::ID),
+                Exit(later::ID),
+            ]
+        );
+        assert_eq!(TASK_NAMES, ["button", "work", "later", "idle", "MyMono"]);
+
+        rtic::host::exit()
+    }
+
+    #[task(binds = GPIOA, priority = 2)]
+    fn button(_: button::Context) {
+        work::spawn().unwrap();
+    }
+
+    #[task]
+    fn work(_: work::Context) {}
+
+    #[task]
+    fn later(_: later::Context) {}
+}
+
+#[test]
+fn tracer() {
+    app::run();
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965)]
+mod app {
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[task(binds = UART0, budget_cycles = 1_000)]
+    fn uart0(_: uart0::Context) {}
+}
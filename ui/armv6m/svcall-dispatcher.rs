@@ -0,0 +1,12 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SVCall])]
+mod app {
+    #[init]
+    fn init(_: init::Context) -> (init::LateResources, init::Monotonics) {
+        (init::LateResources {}, init::Monotonics())
+    }
+
+    #[task]
+    fn a(_: a::Context) {}
+}
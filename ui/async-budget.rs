@@ -0,0 +1,7 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[task(budget_cycles = 1_000)]
+    async fn a(_: a::Context) {}
+}
@@ -0,0 +1,9 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[task]
+    async fn a(_: a::Context) {
+        static mut COUNT: u32 = 0;
+    }
+}
@@ -0,0 +1,14 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[resources]
+    struct Resources {
+        #[task_local]
+        #[init(0)]
+        counter: u32,
+    }
+
+    #[task(resources = [counter])]
+    async fn a(_: a::Context) {}
+}
@@ -0,0 +1,7 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965)]
+mod app {
+    #[task(binds = UART0, budget = Milliseconds(1_u32))]
+    fn uart0(_: uart0::Context) {}
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[channels]
+    struct Channels {
+        #[channel(capacity = 2, spawn = consumer)]
+        samples: u32,
+    }
+
+    #[task(receivers = [samples])]
+    fn consumer(_: consumer::Context, x: u32) {}
+}
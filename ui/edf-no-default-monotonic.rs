@@ -0,0 +1,7 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [#[edf] SSI0])]
+mod app {
+    #[task]
+    fn a(_: a::Context) {}
+}
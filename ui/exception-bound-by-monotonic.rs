@@ -0,0 +1,10 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [PendSV])]
+mod app {
+    #[monotonic(binds = PendSV, default = true)]
+    type MyMono = DwtSystick<8_000_000>;
+
+    #[task]
+    fn a(_: a::Context) {}
+}
@@ -0,0 +1,7 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SysTick])]
+mod app {
+    #[task]
+    fn a(_: a::Context) {}
+}
@@ -0,0 +1,9 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965)]
+mod app {
+    #[idle(budget_cycles = 1_000)]
+    fn idle(_: idle::Context) -> ! {
+        loop {}
+    }
+}
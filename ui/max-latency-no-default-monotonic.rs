@@ -0,0 +1,7 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[task(max_latency = Milliseconds(5_u32))]
+    fn a(_: a::Context) {}
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [#[multiplex] SSI0])]
+mod app {
+    #[task]
+    fn a(_: a::Context) {}
+
+    #[task(priority = 33)]
+    fn b(_: b::Context) {}
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[monotonic(binds = SysTick, default = true)]
+    type MyMono = DwtSystick<8_000_000>;
+
+    #[task(period = Seconds(1_u32), overflow = drop_oldest)]
+    fn a(_: a::Context) {}
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965)]
+#[pool(Buffer, 2)]
+#[pool(Buffer, 4)]
+mod app {}
+
+pub struct Buffer([u8; 64]);
@@ -0,0 +1,16 @@
+#![no_main]
+
+#[rtic::app(device = lm3s6965, dispatchers = [SSI0])]
+mod app {
+    #[monotonic(binds = SysTick, default = true)]
+    type MyMono = DwtSystick<8_000_000>;
+
+    #[task(capacity = 255)]
+    async fn a(_: a::Context) {}
+
+    #[task(capacity = 255)]
+    async fn b(_: b::Context) {}
+
+    #[task(capacity = 255)]
+    async fn c(_: c::Context) {}
+}